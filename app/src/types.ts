@@ -11,6 +11,17 @@ export interface LogEntryRaw {
   domain: string | null;
   remote_ip: string | null;
   source: string | null;
+  alpn?: string;
+  tls_version?: string;
+  ja3?: string;
+  ja4?: string;
+  iface?: string;
+  mac?: string;
+  delayed_dns: boolean; // DNS sem conexão: remote_ip é o resolvedor ou, com answers, o 1º IP resolvido
+  answers?: string[]; // IPs resolvidos
+  dns_ttl?: number;
+  dns_rcode?: string; // ex: NXDOMAIN
+  flags?: string[]; // ex: "via_dns"
   raw: string;
 }
 
@@ -19,7 +30,25 @@ export interface LogEntryDisplay extends LogEntryRaw {
   count: number;
 }
 
-export interface BytesClientEntry {
+export interface Traffic {
+  bytes_in: number;
+  bytes_out: number;
+  bytes_total: number;
+}
+
+// Divisão por protocolo (tcp, udp, icmp, other) e porta de serviço ("443", ..., "other")
+export interface TrafficBreakdown {
+  protocols: Record<string, Traffic>;
+  ports: Record<string, Traffic>;
+}
+
+// Taxa média (bits/s) numa janela: "10s", "1m", "5m"
+export interface Rate {
+  in_bps: number;
+  out_bps: number;
+}
+
+export interface BytesClientEntry extends Partial<TrafficBreakdown> {
   bytes_in: number;
   bytes_out: number;
   bytes_total: number;
@@ -28,21 +57,146 @@ export interface BytesClientEntry {
   mb_total: number;
   last_seen_any: string
   last_seen_out: string
+  iface?: string;
+  ip?: string;
+  mac?: string;
+  vendor?: string;
+  packets_in?: number;
+  packets_out?: number;
+  packets_total?: number;
+  rates?: Record<string, Rate>;
+}
+
+// Nomes aprendidos na rede (DHCP, mDNS, LLMNR, NBNS), em /clients
+export interface LearnedName {
+  name?: string;
+  source?: string;
+  services?: string[];
+  vendor_class?: string;
+  mac?: string;
+  vendor?: string;
+}
+
+export interface ClientsResponse {
+  clients: string[];
+  names?: Record<string, LearnedName>;
+}
+
+// Palpite de sistema/tipo de aparelho (fingerprint passivo)
+export interface FingerprintGuess {
+  os?: string;
+  device?: string; // celular, tablet, computador, tv, console, rede
+}
+
+// Aplicação TLS vista no dispositivo (chave: JA4)
+export interface TlsClient {
+  ja3: string;
+  count: number;
+  first_seen: number;
+  last_seen: number;
+  domains?: string[];
+}
+
+export interface ClientFingerprint extends FingerprintGuess {
+  evidence?: Record<string, FingerprintGuess & { signature: string }>; // syn, dhcp, user_agent
+  tls?: Record<string, TlsClient>;
+  last_seen: number;
+}
+
+// GET /clients/:id
+export interface ClientDetails {
+  id: string;
+  mac?: string;
+  vendor?: string;
+  ips: string[];
+  names: Record<string, LearnedName>;
+  fingerprint?: ClientFingerprint;
+  bytes?: BytesClientEntry;
 }
 
 export interface BytesData {
   updated_at: string | null;
+  period?: string; // "2025-12-01" (diário) ou "2025-12" (mensal); ausente sem virada
   clients: Record<string, BytesClientEntry>;
+  network?: TrafficBreakdown; // rede toda
 }
 
 
 export interface SummaryRow {
+  device: string; // chave em /bytes: MAC, ou IP se o MAC não é conhecido
   ip: string;
+  vendor?: string; // fabricante (OUI) ou "randomized"
   mb_in: number;
   mb_out: number;
   mb_total: number;
+  rate_in_bps: number; // janela de 10s
+  rate_out_bps: number;
   online: boolean;
   last_seen: string | null;
 }
 
 
+
+// GET /flows: conexão (5-tupla) com bytes e pacotes por sentido
+export interface Flow {
+  protocol: string;
+  client: string;
+  client_port: number;
+  remote: string;
+  remote_port: number;
+  domain?: string; // SNI/Host da conexão ou DNS resolvido pelo cliente
+  mac?: string;
+  iface?: string;
+  start: number;
+  end: number;
+  packets_out: number;
+  packets_in: number;
+  bytes_out: number;
+  bytes_in: number;
+  end_reason?: 'fin' | 'rst' | 'timeout'; // ausente = ainda aberto
+}
+
+export interface FlowsResponse {
+  flows: Flow[];
+  active: number;
+}
+
+// GET /bytes/domains: maiores domínios por volume, por dispositivo
+export interface DomainUsage {
+  domain: string; // sem domínio conhecido, o IP remoto (resolved = false)
+  resolved: boolean;
+  bytes_in: number;
+  bytes_out: number;
+  bytes_total: number;
+  flows: number;
+}
+
+export interface TopDomainsResponse {
+  since: number;
+  until: number;
+  clients: Record<string, DomainUsage[]>;
+}
+
+// /bytes/history: soma por intervalo de `step` segundos
+export interface HistoryPoint {
+  ts: number; // início do intervalo (epoch)
+  bytes_in: number;
+  bytes_out: number;
+  bytes_total: number;
+  packets_in: number;
+  packets_out: number;
+}
+
+export interface HistoryResponse {
+  from: number;
+  to: number;
+  step: number;
+  clients: Record<string, HistoryPoint[]>; // só intervalos com tráfego
+}
+
+// /logs: do banco de eventos (filtros since/until/domain, paginação) ou do log do dia
+export interface LogsResponse {
+  entries: LogEntry[];
+  date: string | null;
+  next_offset?: number; // passar como offset para a próxima página
+}
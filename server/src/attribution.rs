@@ -0,0 +1,70 @@
+// IP remoto -> domínio, para atribuir bytes a (cliente, domínio). Aprende dos
+// eventos do DomainPipeline (o mesmo parser de DNS/TLS/QUIC/HTTP do domains):
+// SNI/Host da conexão e IPs das respostas DNS.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::pipeline::DomainEvent;
+
+// Resoluções bem mais velhas que qualquer TTL razoável ainda valem (conexões
+// longas, cache do cliente), mas não para sempre: o IP de CDN muda de dono
+const ENTRY_MAX_AGE: f64 = 6.0 * 3600.0;
+const PRUNE_INTERVAL: f64 = 60.0;
+
+struct Learned {
+    domain: String,
+    ts: f64,
+    from_sni: bool,
+}
+
+#[derive(Default)]
+pub struct DomainMap {
+    by_client: HashMap<(IpAddr, IpAddr), Learned>, // (cliente, remoto)
+    by_remote: HashMap<IpAddr, Learned>,           // qualquer cliente
+    last_prune: f64,
+}
+
+impl DomainMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, ev: &DomainEvent) {
+        if ev.ts - self.last_prune >= PRUNE_INTERVAL {
+            self.by_client.retain(|_, l| ev.ts - l.ts <= ENTRY_MAX_AGE);
+            self.by_remote.retain(|_, l| ev.ts - l.ts <= ENTRY_MAX_AGE);
+            self.last_prune = ev.ts;
+        }
+        let Ok(client) = ev.client.parse::<IpAddr>() else { return };
+        let domain = ev.domain.to_ascii_lowercase();
+        if ev.source == "DNS" {
+            // Sem resposta vista o remoto é o resolvedor: nada a aprender
+            for ip in &ev.answers {
+                self.learn(client, *ip, &domain, ev.ts, false);
+            }
+            return;
+        }
+        // SNI/Host diz exatamente com quem a conexão fala: vence o DNS
+        if let Ok(remote) = ev.remote.parse::<IpAddr>() {
+            self.learn(client, remote, &domain, ev.ts, true);
+        }
+    }
+
+    fn learn(&mut self, client: IpAddr, remote: IpAddr, domain: &str, ts: f64, from_sni: bool) {
+        for slot in [self.by_client.entry((client, remote)).or_insert_with(|| Learned { domain: String::new(), ts, from_sni }),
+                     self.by_remote.entry(remote).or_insert_with(|| Learned { domain: String::new(), ts, from_sni })] {
+            // DNS não sobrescreve um SNI recente do mesmo par
+            if from_sni || !slot.from_sni || slot.domain.is_empty() {
+                slot.domain = domain.to_string();
+                slot.from_sni = from_sni;
+            }
+            slot.ts = ts;
+        }
+    }
+
+    // O que este cliente resolveu/pediu para o IP; senão, o que outro cliente resolveu
+    pub fn lookup(&self, client: &IpAddr, remote: &IpAddr) -> Option<&str> {
+        self.by_client.get(&(*client, *remote)).or_else(|| self.by_remote.get(remote)).map(|l| l.domain.as_str())
+    }
+}
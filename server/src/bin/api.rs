@@ -1,20 +1,30 @@
 use axum::{
-    extract::{Query, Json},
+    extract::{Path as UrlPath, Query, Json},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::net::SocketAddr;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use tower_http::services::ServeDir;
 use tower_http::cors::CorsLayer;
-use traffic_utils::{parse_log_line, ParsedLogEntry}; // Importa da nossa lib
-use chrono::{Local, Duration};
+use traffic_utils::counters::history_path;
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::dhcp::LeaseTable;
+use traffic_utils::eventdb::{EventDb, EventQuery};
+use traffic_utils::events::{jsonl_path, LogEvent};
+use traffic_utils::fingerprint::{ClientFingerprint, FingerprintTable};
+use traffic_utils::flows::{self, Flow};
+use traffic_utils::names::NameTable;
+use traffic_utils::rotation;
+use traffic_utils::timeseries::{TimeSeriesStore, DAY, HOUR, MINUTE};
+use traffic_utils::{normalize_ip, parse_log_line, ParsedLogEntry}; // Importa da nossa lib
+use chrono::{Local, Duration, NaiveDate, NaiveDateTime, TimeZone};
 
 // --- Configurações ---
 const HOST: [u8; 4] = [100, 76, 63, 123];
@@ -28,8 +38,16 @@ fn get_env(key: &str, default: &str) -> String {
 struct Config {
     web_dir: String,
     log_file: String,
+    jsonl_file: String,
+    events_db: String,
     bytes_file: String,
     ignore_file: String,
+    dhcp_leases_file: String,
+    names_file: String,
+    devices_file: String,
+    fingerprints_file: String,
+    flows_dir: String,
+    history_dir: String,
 }
 
 impl Config {
@@ -39,8 +57,16 @@ impl Config {
         Self {
             web_dir: format!("{}/web", base_dir),
             log_file: get_env("LOG_FILE", "/var/log/traffic-domains/traffic-domains.log"),
+            jsonl_file: get_env("LOG_JSONL_FILE", &jsonl_path(&get_env("LOG_FILE", "/var/log/traffic-domains/traffic-domains.log"))),
+            events_db: get_env("EVENTS_DB", "/var/log/traffic-domains/events.db"),
             bytes_file: get_env("BYTES_FILE", "/var/log/traffic-domains/traffic-bytes.json"),
             ignore_file: get_env("IGNORE_FILE", "/etc/traffic-monitor/ignore-domains.txt"),
+            dhcp_leases_file: get_env("DHCP_LEASES_FILE", "/var/log/traffic-domains/dhcp-leases.json"),
+            names_file: get_env("NAMES_FILE", "/var/log/traffic-domains/discovered-names.json"),
+            devices_file: get_env("DEVICES_FILE", "/var/log/traffic-domains/devices.json"),
+            fingerprints_file: get_env("FINGERPRINTS_FILE", "/var/log/traffic-domains/fingerprints.json"),
+            flows_dir: get_env("FLOWS_DIR", "/var/log/traffic-domains/flows"),
+            history_dir: get_env("HISTORY_DIR", "/var/log/traffic-domains/history"),
         }
     }
 }
@@ -58,6 +84,10 @@ async fn main() {
         .route("/log-days", get(handle_log_days))
         .route("/bytes", get(handle_bytes))
         .route("/clients", get(handle_clients))
+        .route("/clients/:id", get(handle_client_details))
+        .route("/bytes/domains", get(handle_top_domains))
+        .route("/bytes/history", get(handle_bytes_history))
+        .route("/flows", get(handle_flows))
         .route("/ignored-domains", get(handle_get_ignored)
             .post(handle_post_ignored)
             .delete(handle_delete_ignored))
@@ -78,62 +108,313 @@ async fn main() {
 struct LogsQuery {
     client: Option<String>,
     limit: Option<usize>,
+    offset: Option<usize>, // paginação: next_offset da resposta anterior
     date: Option<String>,
+    since: Option<String>, // since/until: intervalo de vários dias (no lugar de date)
+    until: Option<String>,
+    domain: Option<String>, // o domínio e seus subdomínios
+    iface: Option<String>,
 }
 
 #[derive(Serialize)]
 struct LogsResponse {
     entries: Vec<ParsedLogEntry>,
     date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>, // há mais eventos além desta página
 }
 
 async fn handle_logs(
     Query(params): Query<LogsQuery>,
     axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
-) -> impl IntoResponse {
+) -> Response {
+    blocking(move || Json(logs_page(&config, params)).into_response()).await
+}
+
+// SQLite, arquivos diários e parse de JSON bloqueiam: fora das threads do executor
+async fn blocking(f: impl FnOnce() -> Response + Send + 'static) -> Response {
+    tokio::task::spawn_blocking(f).await
+        .unwrap_or_else(|_| (StatusCode::INTERNAL_SERVER_ERROR, "falha ao montar a resposta").into_response())
+}
+
+fn logs_page(config: &Config, params: LogsQuery) -> LogsResponse {
     let limit = params.limit.unwrap_or(1000).clamp(1, 5000);
-    let client_filter = params.client.as_deref().unwrap_or("").trim();
+    let offset = params.offset.unwrap_or(0);
+    // IPv6 pode chegar em outra grafia ("2001:DB8:0::1"); compara na forma canônica
+    let device = device_filter(config, params.client.as_deref());
     let date_str = params.date.as_deref().unwrap_or("").trim();
-
-    // Resolve arquivo de log
-    let file_path = resolve_log_path(&config.log_file, date_str);
-    
-    if !file_path.exists() {
-        return Json(LogsResponse { entries: vec![], date: Some(date_str.to_string()) });
+    let iface_filter = params.iface.as_deref().map(str::trim).filter(|i| !i.is_empty() && *i != "all");
+    let domain_filter = params.domain.as_deref().map(|d| d.trim().trim_end_matches('.').to_lowercase()).filter(|d| !d.is_empty());
+    let is_range = params.since.is_some() || params.until.is_some();
+    let (since, until) = if is_range { time_range(params.since.as_deref(), params.until.as_deref()) } else { day_range(date_str) };
+
+    // Banco de eventos: usado quando cobre o início do intervalo (ou sempre, para
+    // intervalos de vários dias, que o log texto não atende)
+    let db = (!config.events_db.is_empty()).then(|| EventDb::open_read_only(&config.events_db).ok()).flatten();
+    if let Some(db) = db.filter(|db| is_range || db.first_ts().ok().flatten().is_some_and(|first| first <= since)) {
+        let mut q = EventQuery {
+            since: Some(since),
+            until: Some(until),
+            domain: domain_filter,
+            iface: iface_filter.map(str::to_string),
+            offset,
+            limit: limit + 1,
+            ..Default::default()
+        };
+        if let Some(d) = &device { d.restrict(&mut q); }
+        let mut events = db.query(&q).unwrap_or_default();
+        let next_offset = (events.len() > limit).then_some(offset + limit);
+        events.truncate(limit);
+        let entries = events.iter().map(LogEvent::to_entry).collect();
+        return LogsResponse { entries, date: Some(date_str.to_string()), next_offset };
     }
 
-    // Lê arquivo (inverte ordem manualmente para pegar os mais recentes)
-    // Nota: Ler arquivo inteiro na RAM pode ser custoso se o log for gigante.
-    // Em produção real, usaríamos `rev_lines` crate ou seek do fim.
-    let content = match fs::read_to_string(&file_path) {
-        Ok(c) => c,
-        Err(_) => return Json(LogsResponse { entries: vec![], date: Some(date_str.to_string()) }),
-    };
+    // Sem banco: log do dia (texto/JSONL), mais recentes primeiro
+    let mut entries: Vec<ParsedLogEntry> = read_log_entries(config, date_str).into_iter().rev()
+        .filter(|e| device.as_ref().is_none_or(|d| d.matches(e)))
+        .filter(|e| iface_filter.is_none_or(|i| e.iface.as_deref() == Some(i)))
+        .filter(|e| domain_filter.as_deref().is_none_or(|d| e.domain == d || e.domain.ends_with(&format!(".{}", d))))
+        .skip(offset)
+        .take(limit + 1)
+        .collect();
+    let next_offset = (entries.len() > limit).then_some(offset + limit);
+    entries.truncate(limit);
+    LogsResponse { entries, date: Some(date_str.to_string()), next_offset }
+}
 
-    let mut entries = Vec::new();
-    // Itera reverso
-    for line in content.lines().rev() {
-        if line.trim().is_empty() { continue; }
-        
-        if let Some(entry) = parse_log_line(line) {
-            if !client_filter.is_empty() && client_filter != "all" && entry.client_ip != client_filter {
-                continue;
-            }
-            entries.push(entry);
-            if entries.len() >= limit { break; }
+// [00:00 do dia, 00:00 do dia seguinte); dia vazio ou inválido = hoje
+fn day_range(date_str: &str) -> (f64, f64) {
+    let day = NaiveDate::parse_from_str(date_str, "%Y-%m-%d").unwrap_or_else(|_| Local::now().date_naive());
+    let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0).and_then(|t| Local.from_local_datetime(&t).earliest()).map_or(0.0, |t| t.timestamp() as f64);
+    (midnight(day), midnight(day + Duration::days(1)))
+}
+
+// Filtro por dispositivo: aceita IP ou MAC e pega o histórico de todos os IPs do aparelho
+struct DeviceFilter {
+    query: String,
+    mac: Option<String>,
+    ips: HashSet<String>,
+}
+
+impl DeviceFilter {
+    fn new(query: &str, devices: &DeviceTable) -> Self {
+        let mac = if query.parse::<std::net::IpAddr>().is_ok() { devices.mac_of(query).map(str::to_string) } else { Some(query.to_string()) };
+        let ips = mac.as_deref().map(|m| devices.ips_of(m)).unwrap_or_default().into_iter().collect();
+        DeviceFilter { query: query.to_string(), mac, ips }
+    }
+
+    // Mesma regra de matches_client, em SQL
+    fn restrict(&self, q: &mut EventQuery) {
+        q.mac = self.mac.clone();
+        q.ips = self.ips.iter().cloned().collect();
+        if self.query.parse::<std::net::IpAddr>().is_ok() && !self.ips.contains(&self.query) {
+            q.ips.push(self.query.clone());
         }
     }
 
-    Json(LogsResponse { entries, date: Some(date_str.to_string()) })
+    fn matches(&self, entry: &ParsedLogEntry) -> bool {
+        self.matches_client(&entry.client_ip, entry.mac.as_deref())
+    }
+
+    fn matches_client(&self, client_ip: &str, line_mac: Option<&str>) -> bool {
+        let ip = normalize_ip(client_ip);
+        match (line_mac, &self.mac) {
+            // Linha com MAC: decide pelo MAC, não importa o IP da época
+            (Some(line_mac), Some(mac)) => line_mac.eq_ignore_ascii_case(mac),
+            (Some(_), None) => ip == self.query,
+            // Linha antiga, sem MAC: vale qualquer IP atual do aparelho
+            (None, _) => ip == self.query || self.ips.contains(&ip),
+        }
+    }
 }
 
-fn resolve_log_path(base_log: &str, date_str: &str) -> PathBuf {
+// Eventos do dia em ordem, do log texto e/ou do JSONL (LOG_FORMAT=both grava os
+// dois; o mesmo evento só entra uma vez). Nota: lê os arquivos inteiros na RAM.
+fn read_log_entries(config: &Config, date_str: &str) -> Vec<ParsedLogEntry> {
     let today = Local::now().format("%Y-%m-%d").to_string();
-    if date_str.is_empty() || date_str == today {
-        return PathBuf::from(base_log);
+    let day = if date_str.is_empty() { today.as_str() } else { date_str };
+    let mut entries: Vec<ParsedLogEntry> = Vec::new();
+    let mut seen = HashSet::new();
+    for base in [&config.jsonl_file, &config.log_file] {
+        let Some(content) = day_content(base, day, &today) else { continue };
+        let first_file = entries.is_empty();
+        // Filtra pelo dia: o arquivo base pode ter outro dia (ainda não rodou, DNS atrasado)
+        for entry in content.lines().filter(|l| !l.trim().is_empty()).filter_map(parse_log_line).filter(|e| e.timestamp.starts_with(day)) {
+            let key = (entry.timestamp.clone(), entry.client_ip.clone(), entry.domain.clone(), entry.remote_ip.clone(), entry.source.clone());
+            if seen.insert(key) || first_file { entries.push(entry); }
+        }
+    }
+    // Texto e JSONL intercalados (ex: formato trocado no meio do dia)
+    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    entries
+}
+
+// Dia passado: o rotacionado (domains roda à meia-noite: traffic-domains.log-2025-12-01[.gz])
+// e também o arquivo base, que guarda o fim do dia até a primeira linha do dia seguinte
+fn day_content(base_log: &str, day: &str, today: &str) -> Option<String> {
+    let rotated = (day != today).then(|| rotation::read_log(&rotation::rotated_path(base_log, day)).ok()).flatten();
+    let current = fs::read_to_string(base_log).ok();
+    match (rotated, current) {
+        (Some(r), Some(c)) => Some(r + &c),
+        (r, c) => r.or(c),
+    }
+}
+
+// --- Handler: Flows ---
+
+#[derive(Deserialize)]
+struct FlowsQuery {
+    client: Option<String>, // IP ou MAC
+    remote: Option<String>,
+    port: Option<u16>,      // porta de qualquer dos lados
+    protocol: Option<String>,
+    since: Option<String>,  // epoch ou "YYYY-MM-DD[ HH:MM:SS]"; padrão: início de hoje
+    until: Option<String>,  // padrão: agora
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct FlowsResponse {
+    flows: Vec<Flow>, // mais recentes primeiro (pelo fim)
+    active: usize,    // quantos dos devolvidos ainda estão abertos
+}
+
+const MAX_FLOW_DAYS: i64 = 31;
+
+fn parse_time(s: &str) -> Option<f64> {
+    let s = s.trim();
+    // "inf"/"NaN" também são f64 válidos: fora
+    if let Ok(epoch) = s.parse::<f64>() { return epoch.is_finite().then_some(epoch); }
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))?;
+    Local.from_local_datetime(&naive).earliest().map(|dt| dt.timestamp() as f64)
+}
+
+// Intervalo pedido: padrão é de 00:00 de hoje até agora
+fn time_range(since: Option<&str>, until: Option<&str>) -> (f64, f64) {
+    let now = Local::now();
+    let until = until.and_then(parse_time).unwrap_or(now.timestamp() as f64);
+    let since = since.and_then(parse_time)
+        .unwrap_or_else(|| now.date_naive().and_hms_opt(0, 0, 0).and_then(|d| Local.from_local_datetime(&d).earliest()).map_or(0.0, |d| d.timestamp() as f64));
+    (since, until)
+}
+
+// Fluxos que se sobrepõem a [since, until]: arquivos diários mais o snapshot dos
+// ainda abertos. O arquivo é o do dia em que o fluxo terminou, que pode ser depois
+// de `until`: lê do dia de `until` até hoje (no máximo MAX_FLOW_DAYS além dele) e
+// para trás até `since` (no máximo MAX_FLOW_DAYS)
+fn load_flows(config: &Config, since: f64, until: f64, wanted: impl Fn(&Flow) -> bool) -> Vec<Flow> {
+    let wanted = |f: &Flow| f.end >= since && f.start <= until && wanted(f);
+    let today = Local::now().date_naive();
+    let first_day = Local.timestamp_opt(since as i64, 0).single().map_or(today, |d| d.date_naive());
+    let last_day = Local.timestamp_opt(until as i64, 0).single().map_or(today, |d| d.date_naive());
+    let newest_day = today.min(last_day.checked_add_signed(Duration::days(MAX_FLOW_DAYS)).unwrap_or(last_day)).max(last_day);
+    let mut flows: Vec<Flow> = Vec::new();
+    let mut day = newest_day;
+    while day >= first_day && (last_day - day).num_days() < MAX_FLOW_DAYS {
+        let path = flows::flows_file(&config.flows_dir, &day.format("%Y-%m-%d").to_string());
+        flows.extend(flows::read_flows(&path).into_iter().filter(|f| wanted(f)));
+        let Some(prev) = day.pred_opt() else { break };
+        day = prev;
+    }
+    let active: Vec<Flow> = fs::read_to_string(flows::active_flows_file(&config.flows_dir)).ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    flows.extend(active.into_iter().filter(|f| wanted(f)));
+    flows
+}
+
+fn device_filter(config: &Config, client: Option<&str>) -> Option<DeviceFilter> {
+    let client_filter = normalize_ip(client.unwrap_or("").trim());
+    (!client_filter.is_empty() && client_filter != "all")
+        .then(|| DeviceFilter::new(&client_filter, &DeviceTable::load(&config.devices_file)))
+}
+
+async fn handle_flows(
+    Query(params): Query<FlowsQuery>,
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
+) -> Response {
+    blocking(move || flows_response(&config, params)).await
+}
+
+fn flows_response(config: &Config, params: FlowsQuery) -> Response {
+    let limit = params.limit.unwrap_or(500).clamp(1, 5000);
+    let (since, until) = time_range(params.since.as_deref(), params.until.as_deref());
+    let device = device_filter(config, params.client.as_deref());
+    let remote_filter = normalize_ip(params.remote.as_deref().unwrap_or("").trim());
+    let protocol_filter = params.protocol.as_deref().unwrap_or("").trim().to_lowercase();
+
+    let mut flows = load_flows(config, since, until, |f| {
+        device.as_ref().is_none_or(|d| d.matches_client(&f.client, f.mac.as_deref()))
+            && (remote_filter.is_empty() || normalize_ip(&f.remote) == remote_filter)
+            && params.port.is_none_or(|p| f.client_port == p || f.remote_port == p)
+            && (protocol_filter.is_empty() || f.protocol == protocol_filter)
+    });
+    flows.sort_by(|a, b| b.end.total_cmp(&a.end));
+    flows.truncate(limit);
+    let active = flows.iter().filter(|f| f.end_reason.is_none()).count();
+    Json(FlowsResponse { flows, active }).into_response()
+}
+
+// --- Handler: Bytes por domínio ---
+
+#[derive(Deserialize)]
+struct TopDomainsQuery {
+    client: Option<String>, // IP ou MAC; sem ele, todos os clientes
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<usize>,   // domínios por cliente
+}
+
+#[derive(Serialize, Default, Clone)]
+struct DomainUsage {
+    domain: String, // sem domínio conhecido, o IP remoto
+    resolved: bool,
+    bytes_in: u64,
+    bytes_out: u64,
+    bytes_total: u64,
+    flows: u64,
+}
+
+#[derive(Serialize)]
+struct TopDomainsResponse {
+    since: f64,
+    until: f64,
+    clients: BTreeMap<String, Vec<DomainUsage>>, // ID do dispositivo -> maiores primeiro
+}
+
+// Soma os fluxos do intervalo por (dispositivo, domínio). Um fluxo que cruza
+// a borda do intervalo entra inteiro.
+async fn handle_top_domains(
+    Query(params): Query<TopDomainsQuery>,
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
+) -> Response {
+    blocking(move || top_domains_response(&config, params)).await
+}
+
+fn top_domains_response(config: &Config, params: TopDomainsQuery) -> Response {
+    let limit = params.limit.unwrap_or(20).clamp(1, 500);
+    let (since, until) = time_range(params.since.as_deref(), params.until.as_deref());
+    let device = device_filter(config, params.client.as_deref());
+    let flows = load_flows(config, since, until, |f| device.as_ref().is_none_or(|d| d.matches_client(&f.client, f.mac.as_deref())));
+
+    let mut usage: BTreeMap<String, BTreeMap<String, DomainUsage>> = BTreeMap::new();
+    for f in flows {
+        let id = f.mac.clone().unwrap_or_else(|| f.client.clone());
+        let (domain, resolved) = match f.domain { Some(d) => (d, true), None => (f.remote, false) };
+        let u = usage.entry(id).or_default().entry(domain.clone()).or_insert_with(|| DomainUsage { domain, resolved, ..Default::default() });
+        u.bytes_in += f.bytes_in;
+        u.bytes_out += f.bytes_out;
+        u.bytes_total += f.bytes_in + f.bytes_out;
+        u.flows += 1;
     }
-    // Formato log rotated: traffic-domains.log-2025-12-01
-    PathBuf::from(format!("{}-{}", base_log, date_str))
+    let clients = usage.into_iter().map(|(id, domains)| {
+        let mut top: Vec<DomainUsage> = domains.into_values().collect();
+        top.sort_by(|a, b| b.bytes_total.cmp(&a.bytes_total).then_with(|| a.domain.cmp(&b.domain)));
+        top.truncate(limit);
+        (id, top)
+    }).collect();
+    Json(TopDomainsResponse { since, until, clients }).into_response()
 }
 
 // --- Handler: Log Days ---
@@ -143,29 +424,60 @@ struct LogDaysResponse { days: Vec<String> }
 
 async fn handle_log_days(
     axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
-) -> impl IntoResponse {
-    let mut days = Vec::new();
-    let today = Local::now().date_naive();
+) -> Response {
+    blocking(move || log_days_response(&config)).await
+}
 
-    // Checa últimos 30 dias
-    for i in 0..30 {
-        let date = today - Duration::days(i);
-        let date_str = date.format("%Y-%m-%d").to_string();
-        let path = resolve_log_path(&config.log_file, &date_str);
-        
-        if path.exists() {
-            days.push(date_str);
-        }
+fn log_days_response(config: &Config) -> Response {
+    // Dias rotacionados (texto ou JSONL, .gz ou não) mais hoje, mais recentes primeiro
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut days: Vec<String> = [&config.log_file, &config.jsonl_file].iter()
+        .flat_map(|base| rotation::rotated_days(base).into_iter().map(|(date, _, _)| date))
+        .collect();
+    if [&config.log_file, &config.jsonl_file].iter().any(|base| Path::new(base).exists()) {
+        days.push(today);
     }
-    Json(LogDaysResponse { days })
+    days.sort();
+    days.dedup();
+    days.reverse();
+    Json(LogDaysResponse { days }).into_response()
 }
 
 // --- Handlers: Bytes/Clients ---
 
+#[derive(Deserialize)]
+struct BytesQuery {
+    iface: Option<String>,
+    client: Option<String>, // IP ou MAC; devolve o dispositivo correspondente
+    period: Option<String>, // período já fechado ("2025-12-01" ou "2025-12"); padrão: o atual
+}
+
 async fn handle_bytes(
+    Query(params): Query<BytesQuery>,
     axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
-) -> impl IntoResponse {
-    match fs::read_to_string(&config.bytes_file) {
+) -> Response {
+    blocking(move || bytes_response(&config, params)).await
+}
+
+fn bytes_response(config: &Config, params: BytesQuery) -> Response {
+    let iface_filter = params.iface.as_deref().unwrap_or("").trim();
+    let client_filter = normalize_ip(params.client.as_deref().unwrap_or("").trim());
+    let by_iface = !iface_filter.is_empty() && iface_filter != "all";
+    let by_client = !client_filter.is_empty() && client_filter != "all";
+    match read_bytes_period(&config.bytes_file, params.period.as_deref().unwrap_or("").trim()) {
+        // Filtros: só os clientes daquele segmento / só o dispositivo pedido
+        Ok(content) if by_iface || by_client => {
+            let device_id = DeviceTable::load(&config.devices_file).device_id(&client_filter);
+            let mut json: serde_json::Value = serde_json::from_str(&content).unwrap_or(serde_json::json!({"updated_at": null, "clients": {}}));
+            if let Some(clients) = json.get_mut("clients").and_then(|c| c.as_object_mut()) {
+                clients.retain(|id, c| {
+                    let field = |k: &str| c.get(k).and_then(|v| v.as_str());
+                    (!by_iface || field("iface") == Some(iface_filter))
+                        && (!by_client || *id == device_id || field("ip") == Some(client_filter.as_str()))
+                });
+            }
+            Json(json).into_response()
+        }
         Ok(content) => Response::builder()
             .header("content-type", "application/json")
             .body(axum::body::Body::from(content))
@@ -174,9 +486,111 @@ async fn handle_bytes(
     }
 }
 
+// Snapshot atual, ou o histórico do período pedido (traffic-bytes-2025-12-01.json)
+fn read_bytes_period(bytes_file: &str, period: &str) -> std::io::Result<String> {
+    let current = fs::read_to_string(bytes_file);
+    if period.is_empty() { return current; }
+    // Só "AAAA-MM[-DD]": o valor vira parte do nome do arquivo
+    if !period.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "período inválido"));
+    }
+    let is_current = current.as_ref().ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok())
+        .is_some_and(|j| j.get("period").and_then(|p| p.as_str()) == Some(period));
+    if is_current { current } else { fs::read_to_string(history_path(bytes_file, period)) }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    client: Option<String>, // IP ou MAC; sem ele, todos os dispositivos
+    from: Option<String>,   // epoch ou "YYYY-MM-DD[ HH:MM:SS]"; padrão: início de hoje
+    to: Option<String>,     // padrão: agora
+    step: Option<String>,   // "1m", "5m", "1h", "1d" ou segundos; padrão conforme o intervalo
+}
+
+#[derive(Serialize)]
+struct HistoryPoint {
+    ts: i64, // início do intervalo
+    bytes_in: u64,
+    bytes_out: u64,
+    bytes_total: u64,
+    packets_in: u64,
+    packets_out: u64,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    from: i64,
+    to: i64,
+    step: i64,
+    clients: BTreeMap<String, Vec<HistoryPoint>>, // ID do dispositivo -> intervalos com tráfego, em ordem
+}
+
+const MAX_HISTORY_POINTS: i64 = 10_000;
+
+fn parse_step(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (num, unit) = match s.char_indices().last()? {
+        (i, 'm') => (&s[..i], MINUTE),
+        (i, 'h') => (&s[..i], HOUR),
+        (i, 'd') => (&s[..i], DAY),
+        _ => (s, 1),
+    };
+    let step = num.parse::<i64>().ok()?.checked_mul(unit)?;
+    // Menor unidade guardada é o minuto
+    (step >= MINUTE && step % MINUTE == 0).then_some(step)
+}
+
+async fn handle_bytes_history(
+    Query(params): Query<HistoryQuery>,
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
+) -> Response {
+    blocking(move || bytes_history_response(&config, params)).await
+}
+
+fn bytes_history_response(config: &Config, params: HistoryQuery) -> Response {
+    let (from, to) = time_range(params.from.as_deref(), params.to.as_deref());
+    let (from, to) = (from as i64, to as i64);
+    // from/to extremos vêm direto da query: sem overflow
+    let span = to.saturating_sub(from);
+    let step = match params.step.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(s) => match parse_step(s) {
+            Some(step) => step,
+            None => return (StatusCode::BAD_REQUEST, "step inválido (ex: 1m, 5m, 1h, 1d)").into_response(),
+        },
+        None if span <= 6 * HOUR => MINUTE,
+        None if span <= 7 * DAY => HOUR,
+        None => DAY,
+    };
+    if span / step > MAX_HISTORY_POINTS {
+        return (StatusCode::BAD_REQUEST, "intervalo grande demais para o step").into_response();
+    }
+    let device = device_filter(config, params.client.as_deref());
+    // ID do histórico é o MAC, ou o IP enquanto o MAC não era conhecido
+    let wanted = |id: &str| device.as_ref().is_none_or(|d| match id.parse::<std::net::IpAddr>() {
+        Ok(_) => d.matches_client(id, None),
+        Err(_) => d.matches_client("", Some(id)),
+    });
+    let clients = TimeSeriesStore::new(&config.history_dir).query(from, to, step, wanted).into_iter()
+        .map(|(id, points)| (id, points.into_iter().map(|(ts, s)| HistoryPoint {
+            ts,
+            bytes_in: s.bytes_in,
+            bytes_out: s.bytes_out,
+            bytes_total: s.bytes_in + s.bytes_out,
+            packets_in: s.packets_in,
+            packets_out: s.packets_out,
+        }).collect()))
+        .collect();
+    Json(HistoryResponse { from, to, step, clients }).into_response()
+}
+
 async fn handle_clients(
     axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
-) -> impl IntoResponse {
+) -> Response {
+    blocking(move || clients_response(&config)).await
+}
+
+fn clients_response(config: &Config) -> Response {
     // Lê o JSON de bytes e extrai keys
     let content = fs::read_to_string(&config.bytes_file).unwrap_or_else(|_| "{}".to_string());
     let json: serde_json::Value = serde_json::from_str(&content).unwrap_or(serde_json::json!({}));
@@ -187,7 +601,108 @@ async fn handle_clients(
         clients.sort();
     }
 
-    Json(serde_json::json!({ "clients": clients }))
+    Json(serde_json::json!({ "clients": clients, "names": learned_names(config) })).into_response()
+}
+
+#[derive(Serialize)]
+struct ClientDetails {
+    id: String, // MAC, ou IP se o MAC não é conhecido
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor: Option<String>,
+    ips: Vec<String>,
+    names: BTreeMap<String, LearnedName>, // por IP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<ClientFingerprint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<serde_json::Value>,
+}
+
+// Tudo o que se sabe de um dispositivo, pedido por IP ou MAC
+async fn handle_client_details(
+    UrlPath(id): UrlPath<String>,
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<Config>>,
+) -> Response {
+    blocking(move || client_details_response(&config, &id)).await
+}
+
+fn client_details_response(config: &Config, id: &str) -> Response {
+    let query = normalize_ip(id.trim());
+    let devices = DeviceTable::load(&config.devices_file);
+    let is_mac = query.parse::<std::net::IpAddr>().is_err();
+    let (mac, ips) = if is_mac {
+        (Some(query.clone()), devices.ips_of(&query))
+    } else {
+        match devices.mac_of(&query) {
+            Some(mac) => (Some(mac.to_string()), devices.ips_of(mac)),
+            None => (None, vec![query.clone()]),
+        }
+    };
+    let id = mac.clone().unwrap_or_else(|| query.clone());
+    let vendor = ips.iter().find_map(|ip| devices.vendor_of(ip)).map(str::to_string);
+
+    let mut names = learned_names(config);
+    names.retain(|ip, _| ips.contains(ip));
+
+    // Antes do vínculo IP <-> MAC o fingerprint pode ter ficado guardado pelo IP
+    let fingerprints = FingerprintTable::load(&config.fingerprints_file);
+    let fingerprint = std::iter::once(&id).chain(&ips).find_map(|k| fingerprints.get(k)).cloned();
+
+    let bytes = fs::read_to_string(&config.bytes_file).ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        .and_then(|mut json| {
+            let clients = json.get_mut("clients")?.as_object_mut()?;
+            let key = std::iter::once(&id).chain(&ips).find(|k| clients.contains_key(*k))?.clone();
+            clients.remove(&key)
+        });
+
+    if mac.is_none() && names.is_empty() && fingerprint.is_none() && bytes.is_none() {
+        return (StatusCode::NOT_FOUND, "Cliente desconhecido").into_response();
+    }
+    Json(ClientDetails { id, mac, vendor, ips, names, fingerprint, bytes }).into_response()
+}
+
+#[derive(Serialize, Default)]
+struct LearnedName {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>, // DHCP, mDNS, LLMNR ou NBNS
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    services: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor: Option<String>, // fabricante pelo OUI, ou "randomized"
+}
+
+// Nomes, serviços e fabricante descobertos na rede, por IP (mesma prioridade do sensor: DHCP > mDNS/LLMNR/NBNS)
+fn learned_names(config: &Config) -> BTreeMap<String, LearnedName> {
+    let mut out: BTreeMap<String, LearnedName> = BTreeMap::new();
+    for (ip, host) in NameTable::load(&config.names_file).hosts {
+        out.insert(ip, LearnedName { name: host.name, source: host.source, services: host.services.into_iter().collect(), ..Default::default() });
+    }
+    // Mais recente por último: com IP reaproveitado, vale o dono atual
+    let mut leases: Vec<_> = LeaseTable::load(&config.dhcp_leases_file).leases.into_values().collect();
+    leases.sort_by(|a, b| a.last_seen.total_cmp(&b.last_seen));
+    for lease in leases {
+        let Some(ip) = lease.ip else { continue };
+        let entry = out.entry(ip).or_default();
+        if lease.hostname.is_some() {
+            entry.name = lease.hostname;
+            entry.source = Some("DHCP".to_string());
+        }
+        entry.vendor_class = lease.vendor_class;
+    }
+    for (ip, binding) in DeviceTable::load(&config.devices_file).bindings {
+        let entry = out.entry(ip).or_default();
+        entry.mac = Some(binding.mac);
+        entry.vendor = binding.vendor;
+    }
+    out
 }
 
 // --- Handlers: Ignored Domains ---
@@ -202,7 +717,7 @@ fn read_ignored_list(path: &str) -> Vec<String> {
     if let Ok(file) = File::open(path) {
         BufReader::new(file)
             .lines()
-            .filter_map(|l| l.ok())
+            .map_while(Result::ok)
             .map(|l| l.trim().to_string())
             .filter(|l| !l.is_empty() && !l.starts_with('#'))
             .collect()
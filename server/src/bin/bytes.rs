@@ -1,120 +1,230 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
-use chrono::{DateTime, Local};
-use regex::Regex;
-use serde::Serialize;
+use std::fs;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
 // Importa lib compartilhada
-use traffic_utils::{get_current_epoch, load_map_from_file, load_set_from_file};
+use traffic_utils::capture::{is_idle, iface_networks, SourceSpec};
+use traffic_utils::lan::{LanMatcher, Segments};
+use traffic_utils::counters::{history_path, service_ports_from_env, Breakdown, ByteCounter, ClientData, Rollover, Traffic, RATE_WINDOWS};
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::dhcp::LeaseTable;
+use traffic_utils::flows::{self, FlowTable};
+use traffic_utils::names::NameTable;
+use traffic_utils::oui::OuiDb;
+use traffic_utils::pipeline::DomainPipeline;
+use traffic_utils::rotation::{apply_retention, RotationConfig};
+use traffic_utils::timeseries::{bucket_start, local_date, Retention, TimeSeriesStore, MINUTE};
+use traffic_utils::{get_current_epoch, load_map_from_file, load_set_from_file, save_json_file};
 
 const FLUSH_INTERVAL: f64 = 5.0;
 
 struct Config {
-    iface: String,
-    lan_regex: Regex,
+    source: SourceSpec,
+    lan: LanMatcher,
     json_output: String,
     ignore_clients_path: String,
     hosts_map_path: String,
+    dhcp_leases_path: String,
+    names_path: String,
+    devices_path: String,
+    oui_path: String,
+    flows_dir: String,
+    rollover: Rollover,
+    history_dir: String,
+    retention: Retention,
+    flow_retention: RotationConfig,
 }
 
-struct ClientData {
-    bytes_in: u64,
-    bytes_out: u64,
-    bytes_total: u64,
-    last_seen_any: f64,
-    last_seen_out: f64,
-}
-
-#[derive(Serialize)]
+// Também lido de volta na partida, para as contagens do período sobreviverem a restart
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 struct ClientJsonStats {
     bytes_in: u64, bytes_out: u64, bytes_total: u64,
     mb_in: f64, mb_out: f64, mb_total: f64,
     #[serde(skip_serializing_if = "Option::is_none")] last_seen_any: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] last_seen_out: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] iface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] vendor: Option<String>,
+    packets_in: u64, packets_out: u64, packets_total: u64,
+    rates: BTreeMap<String, Rate>,        // "10s", "1m", "5m"
+    protocols: BTreeMap<String, Traffic>, // tcp, udp, icmp, other
+    ports: BTreeMap<String, Traffic>,     // porta de serviço ou "other"
 }
 
-#[derive(Serialize)]
-struct JsonRoot { updated_at: String, clients: HashMap<String, ClientJsonStats> }
+// Taxa atual em bits/s, média da janela
+#[derive(Serialize, Deserialize)]
+struct Rate { in_bps: f64, out_bps: f64 }
+
+// BTreeMap: mesma forma de JSON, mas com ordem estável entre execuções.
+// Chave de `clients` é o ID do dispositivo (MAC, ou o IP enquanto o MAC não é conhecido).
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct JsonRoot {
+    updated_at: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    period: String, // "2025-12-01" (diário), "2025-12" (mensal) ou vazio (sem virada)
+    clients: BTreeMap<String, ClientJsonStats>,
+    network: Breakdown,
+}
 
 fn main() {
     let config = load_config();
     if let Some(parent) = std::path::Path::new(&config.json_output).parent() {
         let _ = fs::create_dir_all(parent);
     }
-    println!("[bytes] Monitorando tráfego JSON: {}", config.json_output);
+    println!("[bytes] Monitorando tráfego JSON: {} (fonte: {})", config.json_output, config.source.describe());
+    println!("[bytes] LAN: {}", config.lan.describe());
 
-    let mut stats_map: HashMap<String, ClientData> = HashMap::new();
-    let mut ignore_clients: HashSet<String> = HashSet::new();
+    let mut counter = ByteCounter::new(config.lan.clone());
+    counter.service_ports = service_ports_from_env();
+    counter.segments = Segments::from_env(config.source.ifaces(), iface_networks);
+    if config.source.ifaces().len() > 1 {
+        println!("[bytes] Segmentos: {}", counter.segments.describe());
+    }
+    println!("[bytes] Portas de serviço: {:?}", counter.service_ports);
+    // Vínculos já conhecidos (gravados pelo domains); os novos o contador aprende sozinho
+    counter.devices = DeviceTable::load(&config.devices_path);
+    counter.devices.set_oui(OuiDb::load(&config.oui_path));
+    // Snapshot anterior: se ainda for do período atual, as contagens continuam dele
+    let mut period = restore_snapshot(&config.json_output, config.rollover, &mut counter);
+    if let Some(p) = &period {
+        println!("[bytes] Contadores restaurados: {} clientes (período {})", counter.stats.len(), if p.is_empty() { "único" } else { p });
+    }
+    // Histórico por minuto; dias que ficaram sem redução (parado na virada) são reduzidos agora
+    println!("[bytes] Histórico por minuto em {}", config.history_dir);
+    let mut series = TimeSeriesStore::new(&config.history_dir);
+    series.retention = config.retention;
+    let started = get_current_epoch() as i64;
+    series.compact_pending(&local_date(started));
+    series.prune(started);
+    println!("[bytes] Fluxos em {} (retenção: {} dias)", config.flows_dir, config.flow_retention.keep_days.map_or("∞".to_string(), |d| d.to_string()));
+    apply_retention(flows::flow_days(&config.flows_dir), &config.flow_retention, &local_date(started));
+    let mut minute: Option<i64> = None;
+    let mut flow_table = FlowTable::new(config.lan.clone());
+    flow_table.segments = counter.segments.clone();
+    // Domínio de cada fluxo: mesmo parser do domains (DNS, TLS, QUIC, HTTP)
+    let mut sniffer = DomainPipeline::new(config.lan.clone());
+    let mut finished = Vec::new(); // fluxos fechados desde o último flush
     let mut hosts_map: HashMap<String, String> = HashMap::new();
-    let mut last_flush = get_current_epoch();
+    let mut last_flush = 0.0;
     let mut last_reload = 0.0;
 
-    let tcpdump_line_re = Regex::new(r" IP (\S+) > (\S+): .*? (\d+)$").unwrap();
-
-    let mut child = Command::new("tcpdump")
-        .args(&["-i", &config.iface, "-n", "-tt", "-q", "-l", "ip"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .spawn().expect("Falha tcpdump");
-    
-    let stdout = child.stdout.take().unwrap();
-    let reader = BufReader::new(stdout);
+    let mut source = config.source.open().expect("Falha ao abrir captura");
+    let mut last_ts = 0.0;
 
-    for line_res in reader.lines() {
-        let line = match line_res { Ok(l) => l, Err(_) => break };
-        let now = get_current_epoch();
+    // Erro na captura (ex: interface caiu): grava o estado e sai com erro para o systemd reiniciar
+    let mut capture_failed = false;
+    loop {
+        let pkt = match source.next_packet() {
+            Ok(Some(pkt)) => Some(pkt),
+            Ok(None) => break,
+            Err(e) if is_idle(&e) => None,
+            Err(e) => {
+                println!("[bytes] Erro na captura: {}", e);
+                capture_failed = true;
+                break;
+            }
+        };
+        // Relógio do pacote: ao vivo é o instante da captura, no replay é o gravado no pcap;
+        // com a interface parada, o relógio de parede fecha minutos, dias e períodos
+        let now = pkt.as_ref().map_or_else(get_current_epoch, |p| p.ts);
+        last_ts = now;
+        if last_flush == 0.0 { last_flush = now; }
 
         if now - last_reload >= 10.0 {
-            ignore_clients = load_set_from_file(&config.ignore_clients_path);
-            hosts_map = load_map_from_file(&config.hosts_map_path);
+            counter.ignore_clients = load_set_from_file(&config.ignore_clients_path);
+            flow_table.ignore_clients = counter.ignore_clients.clone();
+            // Manual > DHCP > mDNS/LLMNR/NBNS (as tabelas aprendidas são gravadas pelo domains)
+            hosts_map = NameTable::load(&config.names_path).names();
+            hosts_map.extend(LeaseTable::load(&config.dhcp_leases_path).hostnames());
+            hosts_map.extend(load_map_from_file(&config.hosts_map_path));
             last_reload = now;
         }
 
-        if let Some(caps) = tcpdump_line_re.captures(&line) {
-            let size: u64 = caps[3].parse().unwrap_or(0);
-            if size == 0 { continue; }
-            let src_ip = strip_port(&caps[1]);
-            let dst_ip = strip_port(&caps[2]);
-            
-            let is_src = config.lan_regex.is_match(&src_ip);
-            let is_dst = config.lan_regex.is_match(&dst_ip);
-
-            if (is_src && ignore_clients.contains(&src_ip)) || (is_dst && ignore_clients.contains(&dst_ip)) { continue; }
-
-            if is_src {
-                let e = stats_map.entry(src_ip.clone()).or_insert(ClientData{bytes_in:0,bytes_out:0,bytes_total:0,last_seen_any:0.0,last_seen_out:0.0});
-                e.bytes_out += size; e.bytes_total += size; e.last_seen_any = now; e.last_seen_out = now;
+        // Minuto fechado vai para o histórico; na virada do dia, reduz o dia anterior
+        let this_minute = bucket_start(now as i64, MINUTE);
+        if let Some(m) = minute.filter(|m| *m != this_minute) {
+            series.append_minute(m, &counter.take_interval());
+            if local_date(m) != local_date(this_minute) {
+                series.compact_day(&local_date(m));
+                series.prune(this_minute);
+                apply_retention(flows::flow_days(&config.flows_dir), &config.flow_retention, &local_date(this_minute));
             }
-            if is_dst {
-                let e = stats_map.entry(dst_ip.clone()).or_insert(ClientData{bytes_in:0,bytes_out:0,bytes_total:0,last_seen_any:0.0,last_seen_out:0.0});
-                e.bytes_in += size; e.bytes_total += size; e.last_seen_any = now;
+        }
+        minute = Some(this_minute);
+
+        // Virada de período: fecha o anterior num arquivo de histórico e zera
+        let current = config.rollover.period_of(now);
+        if period.as_deref() != Some(current.as_str()) {
+            if let Some(old) = period.as_deref().filter(|_| !counter.stats.is_empty()) {
+                let history = history_path(&config.json_output, old);
+                let closed_at = counter.stats.values().map(|c| c.last_seen_any).fold(0.0, f64::max);
+                save_json(&history, &counter, &hosts_map, old, closed_at);
+                println!("[bytes] Período {} fechado em {}", old, history);
             }
+            counter.reset();
+            period = Some(current);
+        }
+
+        if let Some(pkt) = &pkt {
+            counter.process(pkt);
+            for ev in sniffer.process(pkt) { flow_table.domains.feed(&ev); }
+            finished.extend(flow_table.process(pkt, &counter.devices));
         }
 
         if now - last_flush >= FLUSH_INTERVAL {
-            save_json(&config.json_output, &stats_map, &hosts_map);
+            save_json(&config.json_output, &counter, &hosts_map, period.as_deref().unwrap_or_default(), now);
+            save_flows(&config.flows_dir, &mut finished, &flow_table);
             last_flush = now;
         }
     }
+
+    // Fim da captura (ex: fim do pcap): grava o estado final
+    if last_ts > 0.0 {
+        save_json(&config.json_output, &counter, &hosts_map, period.as_deref().unwrap_or_default(), last_ts);
+        save_flows(&config.flows_dir, &mut finished, &flow_table);
+    }
+    if let Some(m) = minute {
+        series.append_minute(m, &counter.take_interval());
+    }
+    if source.is_offline() {
+        println!("[bytes] Replay concluído: {}", config.json_output);
+    }
+    if capture_failed {
+        std::process::exit(1);
+    }
 }
 
 fn load_config() -> Config {
+    let iface = env::var("IFACE").unwrap_or_else(|_| "enx00e04c68054d".to_string());
     Config {
-        iface: env::var("IFACE").unwrap_or_else(|_| "enx00e04c68054d".to_string()),
-        lan_regex: Regex::new(&env::var("LAN_REGEX").unwrap_or_else(|_| "^192\\.168\\.1\\.".to_string())).unwrap(),
+        source: SourceSpec::from_args(&iface),
+        lan: LanMatcher::from_env(),
         json_output: env::var("OUTFILE").unwrap_or_else(|_| "/var/log/traffic-domains/traffic-bytes.json".to_string()),
         ignore_clients_path: env::var("IGNORE_CLIENTS_FILE").unwrap_or_else(|_| "/etc/traffic-monitor/ignore-clients.txt".to_string()),
         hosts_map_path: env::var("HOSTS_MAP_FILE").unwrap_or_else(|_| "/etc/traffic-monitor/lan-hosts.txt".to_string()),
+        dhcp_leases_path: env::var("DHCP_LEASES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/dhcp-leases.json".to_string()),
+        names_path: env::var("NAMES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/discovered-names.json".to_string()),
+        devices_path: env::var("DEVICES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/devices.json".to_string()),
+        oui_path: env::var("OUI_FILE").unwrap_or_else(|_| "/usr/share/ieee-data/oui.txt".to_string()),
+        flows_dir: env::var("FLOWS_DIR").unwrap_or_else(|_| "/var/log/traffic-domains/flows".to_string()),
+        rollover: Rollover::from_env(),
+        history_dir: env::var("HISTORY_DIR").unwrap_or_else(|_| "/var/log/traffic-domains/history".to_string()),
+        retention: Retention::from_env(),
+        // FLOW_RETENTION_DAYS / FLOW_RETENTION_MB; sem elas os fluxos ficam
+        flow_retention: RotationConfig::retention_from_env("FLOW"),
     }
 }
 
-fn strip_port(s: &str) -> String {
-    let clean = s.trim_end_matches(':');
-    if let Some(idx) = clean.rfind('.') { return clean[..idx].to_string(); }
-    clean.to_string()
+// Terminados vão para o JSONL do dia; os abertos, para um snapshot que a API lê como "ativos"
+fn save_flows(dir: &str, finished: &mut Vec<flows::Flow>, table: &FlowTable) {
+    flows::append_flows(dir, finished);
+    finished.clear();
+    save_json_file(&flows::active_flows_file(dir).to_string_lossy(), &table.active());
 }
 
 fn format_ts(ts: f64) -> String {
@@ -124,19 +234,59 @@ fn format_ts(ts: f64) -> String {
     local.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
-fn save_json(path: &str, stats: &HashMap<String, ClientData>, hosts: &HashMap<String, String>) {
-    let mut clients_out = HashMap::new();
-    for (ip, data) in stats {
-        clients_out.insert(ip.clone(), ClientJsonStats {
+fn parse_ts(s: &str) -> Option<f64> {
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()?;
+    Local.from_local_datetime(&naive).earliest().map(|dt| dt.timestamp() as f64)
+}
+
+// Recarrega o último traffic-bytes.json gravado. Devolve o período dele (None sem snapshot);
+// o laço principal decide se continua contando nele ou se o fecha como histórico.
+fn restore_snapshot(path: &str, rollover: Rollover, counter: &mut ByteCounter) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let root: JsonRoot = serde_json::from_str(&content).ok()?;
+    // Snapshot de antes do campo `period`: deduz pelo horário da gravação
+    let period = if root.period.is_empty() { rollover.period_of(parse_ts(&root.updated_at)?) } else { root.period };
+    for (id, c) in root.clients {
+        counter.stats.insert(id, ClientData {
+            bytes_in: c.bytes_in, bytes_out: c.bytes_out, bytes_total: c.bytes_total,
+            last_seen_any: c.last_seen_any.as_deref().and_then(parse_ts).unwrap_or_default(),
+            last_seen_out: c.last_seen_out.as_deref().and_then(parse_ts).unwrap_or_default(),
+            iface: c.iface, ip: c.ip, mac: c.mac, vendor: c.vendor,
+            breakdown: Breakdown { protocols: c.protocols, ports: c.ports },
+            packets_in: c.packets_in, packets_out: c.packets_out,
+            ..Default::default()
+        });
+    }
+    counter.network = root.network;
+    Some(period)
+}
+
+fn save_json(path: &str, counter: &ByteCounter, hosts: &HashMap<String, String>, period: &str, updated_at: f64) {
+    let mut clients_out = BTreeMap::new();
+    for (id, data) in &counter.stats {
+        let hostname = data.mac.as_ref().and_then(|m| hosts.get(m))
+            .or_else(|| data.ip.as_ref().and_then(|ip| hosts.get(ip)))
+            .cloned();
+        clients_out.insert(id.clone(), ClientJsonStats {
             bytes_in: data.bytes_in, bytes_out: data.bytes_out, bytes_total: data.bytes_total,
             mb_in: data.bytes_in as f64 / 1_048_576.0, mb_out: data.bytes_out as f64 / 1_048_576.0, mb_total: data.bytes_total as f64 / 1_048_576.0,
             last_seen_any: if data.last_seen_any > 0. { Some(format_ts(data.last_seen_any)) } else { None },
             last_seen_out: if data.last_seen_out > 0. { Some(format_ts(data.last_seen_out)) } else { None },
-            hostname: hosts.get(ip).cloned(),
+            hostname,
+            iface: data.iface.clone(),
+            ip: data.ip.clone(),
+            mac: data.mac.clone(),
+            vendor: data.vendor.clone(),
+            packets_in: data.packets_in, packets_out: data.packets_out, packets_total: data.packets_in + data.packets_out,
+            rates: RATE_WINDOWS.iter().map(|(name, secs)| {
+                let (in_bps, out_bps) = data.rates.bps(updated_at, *secs);
+                (name.to_string(), Rate { in_bps, out_bps })
+            }).collect(),
+            protocols: data.breakdown.protocols.clone(),
+            ports: data.breakdown.ports.clone(),
         });
     }
-    let root = JsonRoot { updated_at: format_ts(get_current_epoch()), clients: clients_out };
-    if let Ok(json) = serde_json::to_string(&root) {
-        if let Ok(mut f) = File::create(path) { let _ = f.write_all(json.as_bytes()); }
-    }
+    let root = JsonRoot { updated_at: format_ts(updated_at), period: period.to_string(), clients: clients_out, network: counter.network.clone() };
+    // Temporário + rename: o snapshot é lido de volta na partida, não pode ficar pela metade
+    save_json_file(path, &root);
 }
\ No newline at end of file
@@ -1,148 +1,231 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::env;
 use std::fs::{self};
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
-use chrono::{TimeZone, Local};
-use regex::Regex;
+use chrono::Local;
 // Importa nossa lib compartilhada
-use traffic_utils::{get_current_epoch, load_map_from_file, load_set_from_file, resolve_client_name};
+use traffic_utils::capture::{is_idle, SourceSpec};
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::dhcp::LeaseTable;
+use traffic_utils::eventdb::{BatchWriter, EventDb};
+use traffic_utils::events::{jsonl_path, LogEvent, LogFormat};
+use traffic_utils::fingerprint::FingerprintTable;
+use traffic_utils::lan::LanMatcher;
+use traffic_utils::logline::LogLine;
+use traffic_utils::names::NameTable;
+use traffic_utils::oui::OuiDb;
+use traffic_utils::pipeline::{DomainEvent, DomainPipeline};
+use traffic_utils::rotation::{local_date, retention_days_from_env, retention_start, RotatingFile, RotationConfig};
+use traffic_utils::{client_name, get_current_epoch, load_map_from_file, load_set_from_file, resolve_client_name};
 
-const GROUP_WINDOW: f64 = 5.0;
 const RELOAD_INTERVAL: f64 = 10.0;
 
 struct Config {
-    iface: String,
-    lan_regex: Regex,
-    gateway_ip: String,
+    source: SourceSpec,
+    lan: LanMatcher,
     log_file: String,
+    log_format: LogFormat,
+    jsonl_file: String,
+    events_db: String,
+    rotation: RotationConfig,
+    events_retention: Option<i64>, // dias no events.db
     ignore_domains_path: String,
     ignore_clients_path: String,
     hosts_map_path: String,
-}
-
-struct DnsPending {
-    timestamp: f64,
-    remote_ip: String,
+    dhcp_leases_path: String,
+    names_path: String,
+    devices_path: String,
+    fingerprints_path: String,
+    oui_path: String,
 }
 
 fn main() {
     let config = load_config();
     
-    if let Some(parent) = std::path::Path::new(&config.log_file).parent() {
-        let _ = fs::create_dir_all(parent);
+    for path in [&config.log_file, &config.jsonl_file] {
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
     }
-    println!("[domains] Monitorando domínios na interface: {}", config.iface);
+    println!("[domains] Monitorando domínios em: {}", config.source.describe());
+    println!("[domains] LAN: {}", config.lan.describe());
+    if config.log_format.jsonl() { println!("[domains] Eventos JSONL: {}", config.jsonl_file); }
 
-    let mut ignore_domains: HashSet<String> = HashSet::new();
-    let mut ignore_clients: HashSet<String> = HashSet::new();
     let mut hosts_map: HashMap<String, String> = HashMap::new();
+    let mut leases = LeaseTable::load(&config.dhcp_leases_path);
+    let mut names = NameTable::load(&config.names_path);
+    let mut learned = learned_names(&leases, &names);
     let mut last_reload = 0.0;
-    
-    let mut dns_cache: HashMap<(String, String), DnsPending> = HashMap::new();
-    let mut last_log_map: HashMap<String, f64> = HashMap::new();
-
-    let mut child = Command::new("tshark")
-        .args(&[
-            "-i", &config.iface, "-n", "-l", "-T", "fields",
-            "-e", "frame.time_epoch", "-e", "ip.src", "-e", "ip.dst",
-            "-e", "dns.qry.name", "-e", "tls.handshake.extensions_server_name", "-e", "http.host",
-            "-Y", "dns.qry.name or tls.handshake.extensions_server_name or http.host"
-        ])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .spawn()
-        .expect("Falha ao iniciar tshark");
-
-    let stdout = child.stdout.take().unwrap();
-    let reader = BufReader::new(stdout);
-
-    for line_result in reader.lines() {
-        let line = match line_result { Ok(l) => l, Err(_) => break };
-        let now = get_current_epoch();
+    let mut db_day = String::new();
+    let mut pipeline = DomainPipeline::new(config.lan.clone());
+    pipeline.devices = DeviceTable::load(&config.devices_path);
+    let oui = OuiDb::load(&config.oui_path);
+    println!("[domains] OUI: {} fabricantes ({})", oui.len(), config.oui_path);
+    pipeline.devices.set_oui(oui);
+    let mut fingerprints = FingerprintTable::load(&config.fingerprints_path);
+    // Banco de eventos para o /logs (EVENTS_DB vazio desliga)
+    let db = match config.events_db.as_str() {
+        "" => None,
+        path => match EventDb::open(path) {
+            Ok(db) => {
+                println!("[domains] Eventos SQLite: {} (retenção: {} dias)", path, config.events_retention.map_or("∞".to_string(), |d| d.to_string()));
+                Some(BatchWriter::new(db))
+            }
+            Err(e) => {
+                println!("[domains] Falha ao abrir {}: {} (seguindo sem o banco)", path, e);
+                None
+            }
+        },
+    };
+
+    // Logs rodam à meia-noite (nome-AAAA-MM-DD[.gz]); dias vencidos saem já na partida
+    let log_file = |path: &str| {
+        let file = RotatingFile::new(path, config.rotation);
+        file.maintain(&Local::now().format("%Y-%m-%d").to_string());
+        file
+    };
+    let mut sinks = Sinks {
+        text: config.log_format.text().then(|| log_file(&config.log_file)),
+        jsonl: config.log_format.jsonl().then(|| log_file(&config.jsonl_file)),
+        db,
+    };
+    println!("[domains] Rotação: gzip={} retenção={} dias / {} MB", config.rotation.gzip,
+             config.rotation.keep_days.map_or("∞".to_string(), |d| d.to_string()),
+             config.rotation.max_bytes.map_or("∞".to_string(), |b| (b / 1_048_576).to_string()));
+
+    let mut source = config.source.open().expect("Falha ao abrir captura");
+
+    // Erro na captura (ex: interface caiu): grava o estado e sai com erro para o systemd reiniciar
+    let mut capture_failed = false;
+    loop {
+        let pkt = match source.next_packet() {
+            Ok(Some(pkt)) => Some(pkt),
+            Ok(None) => break,
+            Err(e) if is_idle(&e) => None,
+            Err(e) => {
+                println!("[domains] Erro na captura: {}", e);
+                capture_failed = true;
+                break;
+            }
+        };
+        // Relógio do pacote: ao vivo é o instante da captura, no replay é o gravado no pcap;
+        // com a interface parada, o relógio de parede mantém a rotação e a retenção em dia
+        let now = pkt.as_ref().map_or_else(get_current_epoch, |p| p.ts);
 
         if now - last_reload >= RELOAD_INTERVAL {
-            ignore_domains = load_set_from_file(&config.ignore_domains_path);
-            ignore_clients = load_set_from_file(&config.ignore_clients_path);
+            pipeline.ignore_domains = load_set_from_file(&config.ignore_domains_path);
+            pipeline.ignore_clients = load_set_from_file(&config.ignore_clients_path);
             hosts_map = load_map_from_file(&config.hosts_map_path);
+            pipeline.devices.save_if_dirty(&config.devices_path);
+            fingerprints.save_if_dirty(&config.fingerprints_path);
             last_reload = now;
-        }
-
-        let fields: Vec<&str> = line.split('\t').collect();
-        if fields.len() < 3 { continue; }
-
-        let ts_pkt: f64 = fields[0].parse().unwrap_or(now);
-        let src = fields[1];
-        let dst = fields[2];
-        let dns_name = fields.get(3).unwrap_or(&"");
-        let tls_sni = fields.get(4).unwrap_or(&"");
-        let http_host = fields.get(5).unwrap_or(&"");
-
-        let (domain, fonte) = if !dns_name.is_empty() { (*dns_name, "DNS") }
-                              else if !tls_sni.is_empty() { (*tls_sni, "TLS") }
-                              else if !http_host.is_empty() { (*http_host, "HTTP") }
-                              else { continue; };
-
-        if ignore_domains.contains(domain) { continue; }
-
-        let (client, remote) = if config.lan_regex.is_match(src) && !config.lan_regex.is_match(dst) { (src, dst) }
-        else if config.lan_regex.is_match(dst) && !config.lan_regex.is_match(src) { (dst, src) }
-        else if config.lan_regex.is_match(src) && config.lan_regex.is_match(dst) { (src, dst) }
-        else { continue; };
-
-        if ignore_clients.contains(client) { continue; }
-
-        // Limpeza Cache DNS
-        let expired: Vec<_> = dns_cache.iter()
-            .filter(|(_, v)| ts_pkt - v.timestamp > GROUP_WINDOW)
-            .map(|(k, _)| k.clone()).collect();
-        for k in expired {
-            if let Some(v) = dns_cache.remove(&k) {
-                write_log(&config.log_file, v.timestamp, &k.0, &k.1, &v.remote_ip, "DNS", &hosts_map, true);
+            // Virada do dia pelo relógio, sem esperar o próximo evento; o banco perde o que saiu da retenção
+            for f in [sinks.text.as_mut(), sinks.jsonl.as_mut()].into_iter().flatten() { f.roll(now); }
+            let day = local_date(now);
+            if day != db_day {
+                prune_db(&sinks, config.events_retention, &day);
+                db_day = day;
             }
         }
 
-        // Deduplicação
-        let cache_key = (client.to_string(), domain.to_string());
-        if fonte == "DNS" && remote == config.gateway_ip {
-            dns_cache.insert(cache_key, DnsPending { timestamp: ts_pkt, remote_ip: remote.to_string() });
+        let Some(pkt) = pkt else {
+            if let Some(w) = sinks.db.as_mut() { report_db(w.flush_if_due(now)); }
             continue;
-        } else if fonte == "TLS" || fonte == "HTTP" {
-            dns_cache.remove(&cache_key);
-        }
-
-        let log_key = format!("{}|{}|{}|{}", client, domain, remote, fonte);
-        if let Some(&last_ts) = last_log_map.get(&log_key) {
-            if ts_pkt - last_ts <= 1.0 { continue; }
+        };
+
+        // DHCP e anúncios de nome são raros; persiste a cada mudança para o bytes e a API lerem
+        let leases_changed = leases.feed(&pkt);
+        if leases_changed { leases.save(&config.dhcp_leases_path); }
+        let names_changed = names.feed(&pkt);
+        if names_changed { names.save(&config.names_path); }
+        if leases_changed || names_changed { learned = learned_names(&leases, &names); }
+
+        for ev in pipeline.process(&pkt) {
+            write_log(&mut sinks, &ev, &hosts_map, &learned);
+            if let (Some(ja3), Some(ja4)) = (&ev.ja3, &ev.ja4) {
+                if fingerprints.record_tls(&ev.client, &pipeline.devices, ja3, ja4, &ev.domain, ev.ts) {
+                    let who = resolve_client_name(&ev.client, ev.mac.as_deref(), &hosts_map, &learned);
+                    println!("[domains] Novo cliente TLS em {}: ja4={} ({})", who, ja4, ev.domain);
+                }
+            }
         }
-        last_log_map.insert(log_key, ts_pkt);
+        fingerprints.feed(&pkt, &config.lan, &pipeline.devices);
+        if let Some(w) = sinks.db.as_mut() { report_db(w.flush_if_due(now)); }
+    }
 
-        write_log(&config.log_file, ts_pkt, client, domain, remote, fonte, &hosts_map, false);
+    // Fim da captura (ex: fim do pcap): DNS ainda pendentes saem como "via DNS"
+    for ev in pipeline.flush() {
+        write_log(&mut sinks, &ev, &hosts_map, &learned);
+    }
+    if let Some(w) = sinks.db.as_mut() { report_db(w.flush()); }
+    pipeline.devices.save_if_dirty(&config.devices_path);
+    fingerprints.save_if_dirty(&config.fingerprints_path);
+    if source.is_offline() {
+        println!("[domains] Replay concluído: {}", config.log_file);
+    }
+    if capture_failed {
+        std::process::exit(1);
     }
 }
 
 fn load_config() -> Config {
+    let iface = env::var("IFACE").unwrap_or_else(|_| "enx00e04c68054d".to_string());
+    let log_file = env::var("LOG_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/traffic-domains.log".to_string());
     Config {
-        iface: env::var("IFACE").unwrap_or_else(|_| "enx00e04c68054d".to_string()),
-        lan_regex: Regex::new(&env::var("LAN_REGEX").unwrap_or_else(|_| "^192\\.168\\.1\\.".to_string())).unwrap(),
-        gateway_ip: env::var("GATEWAY_IP").unwrap_or_else(|_| "192.168.1.1".to_string()),
-        log_file: env::var("LOG_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/traffic-domains.log".to_string()),
+        source: SourceSpec::from_args(&iface),
+        lan: LanMatcher::from_env(),
+        log_format: LogFormat::from_env(),
+        jsonl_file: env::var("LOG_JSONL_FILE").unwrap_or_else(|_| jsonl_path(&log_file)),
+        rotation: RotationConfig::from_env(),
+        // EVENTS_RETENTION_DAYS; sem ela o banco guarda tudo
+        events_retention: retention_days_from_env("EVENTS_RETENTION_DAYS"),
+        events_db: env::var("EVENTS_DB").unwrap_or_else(|_| "/var/log/traffic-domains/events.db".to_string()),
+        log_file,
         ignore_domains_path: env::var("IGNORE_FILE").unwrap_or_else(|_| "/etc/traffic-monitor/ignore-domains.txt".to_string()),
         ignore_clients_path: env::var("IGNORE_CLIENTS_FILE").unwrap_or_else(|_| "/etc/traffic-monitor/ignore-clients.txt".to_string()),
         hosts_map_path: env::var("HOSTS_MAP_FILE").unwrap_or_else(|_| "/etc/traffic-monitor/lan-hosts.txt".to_string()),
+        dhcp_leases_path: env::var("DHCP_LEASES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/dhcp-leases.json".to_string()),
+        names_path: env::var("NAMES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/discovered-names.json".to_string()),
+        devices_path: env::var("DEVICES_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/devices.json".to_string()),
+        fingerprints_path: env::var("FINGERPRINTS_FILE").unwrap_or_else(|_| "/var/log/traffic-domains/fingerprints.json".to_string()),
+        oui_path: env::var("OUI_FILE").unwrap_or_else(|_| "/usr/share/ieee-data/oui.txt".to_string()),
     }
 }
 
-fn write_log(logfile: &str, ts: f64, client: &str, domain: &str, remote: &str, fonte: &str, map: &HashMap<String, String>, delayed: bool) {
-    let client_display = resolve_client_name(client, map);
-    let dt = Local.timestamp_opt(ts as i64, 0).unwrap();
-    let ts_str = dt.format("%Y-%m-%d %H:%M:%S").to_string();
-    let line = if delayed {
-        format!("[+] {} | {} → {} (via DNS: {}) | fonte={}\n", ts_str, client_display, domain, remote, fonte)
-    } else {
-        format!("[+] {} | {} → {} ({}) | fonte={}\n", ts_str, client_display, domain, remote, fonte)
-    };
-    if let Ok(mut f) = fs::OpenOptions::new().create(true).append(true).open(logfile) {
-        let _ = f.write_all(line.as_bytes());
+// Nomes aprendidos na rede: o hostname do DHCP vence o de mDNS/LLMNR/NBNS
+fn learned_names(leases: &LeaseTable, names: &NameTable) -> HashMap<String, String> {
+    let mut learned = names.names();
+    learned.extend(leases.hostnames());
+    learned
+}
+
+// Destinos de cada evento, conforme LOG_FORMAT e EVENTS_DB
+struct Sinks {
+    text: Option<RotatingFile>,
+    jsonl: Option<RotatingFile>,
+    db: Option<BatchWriter>,
+}
+
+fn write_log(sinks: &mut Sinks, ev: &DomainEvent, map: &HashMap<String, String>, learned: &HashMap<String, String>) {
+    let name = client_name(&ev.client, ev.mac.as_deref(), map, learned);
+    if sinks.jsonl.is_some() || sinks.db.is_some() {
+        let event = LogEvent::from_event(ev, name);
+        if let Some(f) = sinks.jsonl.as_mut() { f.append(ev.ts, &event.to_line()); }
+        if let Some(w) = sinks.db.as_mut() { report_db(w.push(event)); }
     }
-}
\ No newline at end of file
+    if let Some(f) = sinks.text.as_mut() {
+        f.append(ev.ts, &LogLine::from_event(ev, name).format());
+    }
+}
+
+fn prune_db(sinks: &Sinks, keep_days: Option<i64>, today: &str) {
+    let (Some(w), Some(before)) = (sinks.db.as_ref(), retention_start(keep_days, today)) else { return };
+    match w.prune(before) {
+        Ok(0) => {}
+        Ok(n) => println!("[domains] Retenção: {} eventos antigos apagados do SQLite", n),
+        Err(e) => println!("[domains] Falha ao aplicar a retenção no SQLite: {}", e),
+    }
+}
+
+fn report_db(result: rusqlite::Result<()>) {
+    if let Err(e) = result { println!("[domains] Falha ao gravar eventos no SQLite: {}", e); }
+}
@@ -0,0 +1,417 @@
+// Fontes de pacotes: captura nativa via AF_PACKET (Linux), replay de .pcap,
+// linhas de campos do tshark (stdin) e gerador sintético para testes
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use crate::get_current_epoch;
+use crate::lan::Cidr;
+use crate::packet::{decode_ethernet, decode_linux_sll, decode_raw_ip, DomainHint, Packet};
+
+// Tudo que alimenta os pipelines de domínios e bytes implementa isto.
+// Ok(None) indica fim da fonte (fim do arquivo / stdin). Ao vivo, um erro
+// TimedOut só quer dizer que nada chegou em READ_TIMEOUT_SECS (ver is_idle).
+pub trait PacketSource {
+    fn next_packet(&mut self) -> io::Result<Option<Packet>>;
+
+    // Fontes offline não têm "tempo real": o relógio é o do pacote
+    fn is_offline(&self) -> bool { true }
+}
+
+const SNAPLEN: usize = 65536;
+
+// Espera máxima por um pacote ao vivo: com a interface parada, o loop ainda
+// acorda para rodar logs, retenção e flushes pelo relógio
+pub const READ_TIMEOUT_SECS: i32 = 1;
+
+// Captura ociosa (estourou READ_TIMEOUT_SECS): não é fim nem falha da fonte
+pub fn is_idle(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut
+}
+
+pub struct LiveCapture {
+    fd: libc::c_int,
+    iface: Arc<str>,
+    buf: Vec<u8>,
+}
+
+impl LiveCapture {
+    // Abre um socket raw na interface e liga o modo promíscuo (como o tshark faz)
+    pub fn open(iface: &str) -> io::Result<Self> {
+        let name = CString::new(iface).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "nome de interface inválido"))?;
+        let protocol = (libc::ETH_P_ALL as u16).to_be() as libc::c_int;
+
+        unsafe {
+            let ifindex = libc::if_nametoindex(name.as_ptr());
+            if ifindex == 0 { return Err(io::Error::last_os_error()); }
+
+            let fd = libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, protocol);
+            if fd < 0 { return Err(io::Error::last_os_error()); }
+            // A partir daqui o Drop fecha o fd em caso de erro
+            let capture = LiveCapture { fd, iface: iface.into(), buf: vec![0u8; SNAPLEN] };
+
+            let mut addr: libc::sockaddr_ll = std::mem::zeroed();
+            addr.sll_family = libc::AF_PACKET as u16;
+            addr.sll_protocol = protocol as u16;
+            addr.sll_ifindex = ifindex as i32;
+            let rc = libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            );
+            if rc < 0 { return Err(io::Error::last_os_error()); }
+
+            let mut mreq: libc::packet_mreq = std::mem::zeroed();
+            mreq.mr_ifindex = ifindex as i32;
+            mreq.mr_type = libc::PACKET_MR_PROMISC as u16;
+            let rc = libc::setsockopt(
+                fd,
+                libc::SOL_PACKET,
+                libc::PACKET_ADD_MEMBERSHIP,
+                &mreq as *const libc::packet_mreq as *const libc::c_void,
+                std::mem::size_of::<libc::packet_mreq>() as libc::socklen_t,
+            );
+            if rc < 0 { return Err(io::Error::last_os_error()); }
+
+            let timeout = libc::timeval { tv_sec: READ_TIMEOUT_SECS as libc::time_t, tv_usec: 0 };
+            let rc = libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            );
+            if rc < 0 { return Err(io::Error::last_os_error()); }
+
+            Ok(capture)
+        }
+    }
+
+    // Lê um quadro (espera até READ_TIMEOUT_SECS); None se não for IP decodificável
+    fn recv_one(&mut self) -> io::Result<Option<Packet>> {
+        let n = unsafe { libc::recv(self.fd, self.buf.as_mut_ptr() as *mut libc::c_void, self.buf.len(), 0) };
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted { return Ok(None); }
+            if err.kind() == io::ErrorKind::WouldBlock { return Err(io::ErrorKind::TimedOut.into()); }
+            return Err(err);
+        }
+        Ok(decode_ethernet(get_current_epoch(), &self.buf[..n as usize]).map(|mut pkt| {
+            pkt.iface = Some(self.iface.clone());
+            pkt
+        }))
+    }
+}
+
+impl PacketSource for LiveCapture {
+    // Bloqueia até chegar um pacote IP decodificável ou estourar o READ_TIMEOUT_SECS
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some(pkt) = self.recv_one()? {
+                return Ok(Some(pkt));
+            }
+        }
+    }
+
+    fn is_offline(&self) -> bool { false }
+}
+
+impl Drop for LiveCapture {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
+
+// Redes IPv4/IPv6 configuradas na interface (endereço + máscara); vazio se ela não tem IP
+pub fn iface_networks(iface: &str) -> Vec<Cidr> {
+    let mut nets = Vec::new();
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    unsafe {
+        if libc::getifaddrs(&mut addrs) != 0 { return nets; }
+        let mut cur = addrs;
+        while !cur.is_null() {
+            let ifa = &*cur;
+            cur = ifa.ifa_next;
+            if ifa.ifa_addr.is_null() || ifa.ifa_netmask.is_null() { continue; }
+            if CStr::from_ptr(ifa.ifa_name).to_str() != Ok(iface) { continue; }
+            let (Some(addr), Some(mask)) = (sockaddr_ip(ifa.ifa_addr), sockaddr_ip(ifa.ifa_netmask)) else { continue };
+            let len = match mask {
+                IpAddr::V4(m) => u32::from(m).count_ones(),
+                IpAddr::V6(m) => u128::from(m).count_ones(),
+            };
+            if let Some(net) = Cidr::new(addr, len as u8) { nets.push(net); }
+        }
+        libc::freeifaddrs(addrs);
+    }
+    nets
+}
+
+unsafe fn sockaddr_ip(sa: *const libc::sockaddr) -> Option<IpAddr> {
+    match (*sa).sa_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = &*(sa as *const libc::sockaddr_in);
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))))
+        }
+        libc::AF_INET6 => {
+            let sin6 = &*(sa as *const libc::sockaddr_in6);
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+// Várias interfaces no mesmo processo (ex: br-lan + br-guest): um poll() sobre
+// todos os sockets, cada pacote sai marcado com a interface de origem
+pub struct MultiCapture {
+    captures: Vec<LiveCapture>,
+    ready: Vec<usize>, // índices com dados, ainda não lidos nesta rodada
+}
+
+impl MultiCapture {
+    pub fn open(ifaces: &[String]) -> io::Result<Self> {
+        if ifaces.is_empty() { return Err(io::Error::new(io::ErrorKind::InvalidInput, "nenhuma interface informada")); }
+        let captures = ifaces.iter().map(|i| LiveCapture::open(i)).collect::<io::Result<Vec<_>>>()?;
+        Ok(MultiCapture { captures, ready: Vec::new() })
+    }
+
+    fn poll(&mut self) -> io::Result<()> {
+        let mut fds: Vec<libc::pollfd> = self.captures.iter()
+            .map(|c| libc::pollfd { fd: c.fd, events: libc::POLLIN, revents: 0 })
+            .collect();
+        let rc = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, READ_TIMEOUT_SECS * 1000) };
+        if rc < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted { return Ok(()); }
+            return Err(err);
+        }
+        if rc == 0 { return Err(io::ErrorKind::TimedOut.into()); }
+        if let Some(c) = fds.iter().position(|p| p.revents & libc::POLLNVAL != 0) {
+            return Err(io::Error::other(format!("socket inválido em {}", self.captures[c].iface)));
+        }
+        // POLLERR (ex: interface caiu) também entra: o recv() devolve o erro em vez de girar no poll()
+        // Ordem reversa porque `ready` é consumido com pop()
+        self.ready = fds.iter().enumerate().rev().filter(|(_, p)| p.revents & (libc::POLLIN | libc::POLLERR) != 0).map(|(i, _)| i).collect();
+        Ok(())
+    }
+}
+
+impl PacketSource for MultiCapture {
+    // Um pacote por interface pronta a cada rodada, para uma não monopolizar a outra
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            match self.ready.pop() {
+                Some(i) => {
+                    if let Some(pkt) = self.captures[i].recv_one()? {
+                        return Ok(Some(pkt));
+                    }
+                }
+                None => self.poll()?,
+            }
+        }
+    }
+
+    fn is_offline(&self) -> bool { false }
+}
+
+// --- Replay de arquivo pcap (formato clássico da libpcap) ---
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const MAX_SNAPLEN: usize = 262144; // maior snaplen do libpcap
+
+pub struct PcapFile {
+    reader: BufReader<File>,
+    swapped: bool,
+    nanos: bool,
+    linktype: u32,
+    snaplen: usize, // teto de incl_len: registro maior é arquivo corrompido
+    buf: Vec<u8>,
+}
+
+impl PcapFile {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let (swapped, nanos) = match magic {
+            0xa1b2c3d4 => (false, false),
+            0xd4c3b2a1 => (true, false),
+            0xa1b23c4d => (false, true),
+            0x4d3cb2a1 => (true, true),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "formato não suportado (esperado pcap clássico, não pcapng)")),
+        };
+        let mut file = PcapFile { reader, swapped, nanos, linktype: 0, snaplen: MAX_SNAPLEN, buf: Vec::new() };
+        file.linktype = file.u32_at(&header, 20);
+        file.snaplen = match file.u32_at(&header, 16) as usize { 0 => MAX_SNAPLEN, n => n.min(MAX_SNAPLEN) };
+        match file.linktype {
+            LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_LINUX_SLL => Ok(file),
+            other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("linktype {} não suportado", other))),
+        }
+    }
+
+    fn u32_at(&self, data: &[u8], off: usize) -> u32 {
+        let b = [data[off], data[off + 1], data[off + 2], data[off + 3]];
+        if self.swapped { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+    }
+
+    // Lê o cabeçalho do próximo registro; false no fim do arquivo
+    fn read_record_header(&mut self, rec: &mut [u8; 16]) -> io::Result<bool> {
+        match self.reader.read_exact(rec) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl PacketSource for PcapFile {
+    // Próximo pacote IP do arquivo, com o timestamp gravado na captura
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let mut rec = [0u8; 16];
+            if !self.read_record_header(&mut rec)? { return Ok(None); }
+            let ts_sec = self.u32_at(&rec, 0) as f64;
+            let ts_frac = self.u32_at(&rec, 4) as f64;
+            let incl_len = self.u32_at(&rec, 8) as usize;
+            if incl_len > self.snaplen {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("registro de {} bytes passa do snaplen {}", incl_len, self.snaplen)));
+            }
+
+            self.buf.resize(incl_len, 0);
+            self.reader.read_exact(&mut self.buf)?;
+
+            let ts = ts_sec + if self.nanos { ts_frac / 1e9 } else { ts_frac / 1e6 };
+            let decoded = match self.linktype {
+                LINKTYPE_ETHERNET => decode_ethernet(ts, &self.buf),
+                LINKTYPE_LINUX_SLL => decode_linux_sll(ts, &self.buf),
+                _ => decode_raw_ip(ts, &self.buf),
+            };
+            if let Some(pkt) = decoded {
+                return Ok(Some(pkt));
+            }
+        }
+    }
+}
+
+// --- Linhas de campos do tshark (formato antigo do domains.rs) ---
+// tshark -T fields -e frame.time_epoch -e ip.src -e ip.dst
+//        -e dns.qry.name -e tls.handshake.extensions_server_name -e http.host
+
+pub struct TsharkLines<R: BufRead> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> TsharkLines<R> {
+    pub fn new(reader: R) -> Self {
+        TsharkLines { reader, line: String::new() }
+    }
+}
+
+impl<R: BufRead> PacketSource for TsharkLines<R> {
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 { return Ok(None); }
+            if let Some(pkt) = parse_tshark_fields(self.line.trim_end_matches(['\r', '\n'])) {
+                return Ok(Some(pkt));
+            }
+        }
+    }
+}
+
+pub fn parse_tshark_fields(line: &str) -> Option<Packet> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 3 { return None; }
+
+    let ts: f64 = fields[0].parse().unwrap_or_else(|_| get_current_epoch());
+    let src: IpAddr = fields[1].parse().ok()?;
+    let dst: IpAddr = fields[2].parse().ok()?;
+    let field = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+
+    let hint = if let Some(d) = field(3) { Some(DomainHint { domain: d.to_string(), source: "DNS" }) }
+               else if let Some(d) = field(4) { Some(DomainHint { domain: d.to_string(), source: "TLS" }) }
+               else { field(5).map(|d| DomainHint { domain: d.to_string(), source: "HTTP" }) };
+
+    let mut pkt = Packet::new(ts, src, dst, 0);
+    pkt.hint = hint;
+    Some(pkt)
+}
+
+// --- Gerador sintético (fixtures de teste, carga artificial) ---
+
+pub struct SyntheticSource<I: Iterator<Item = Packet>> {
+    packets: I,
+}
+
+impl<I: Iterator<Item = Packet>> SyntheticSource<I> {
+    pub fn new(packets: impl IntoIterator<Item = Packet, IntoIter = I>) -> Self {
+        SyntheticSource { packets: packets.into_iter() }
+    }
+}
+
+impl<I: Iterator<Item = Packet>> PacketSource for SyntheticSource<I> {
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        Ok(self.packets.next())
+    }
+}
+
+// --- Seleção da fonte pela linha de comando / ambiente ---
+
+pub enum SourceSpec {
+    Live(Vec<String>),
+    PcapFile(String),
+    TsharkStdin,
+}
+
+impl SourceSpec {
+    // `--read <arquivo.pcap>` (ou PCAP_FILE) faz replay; `--stdin` (ou CAPTURE_SOURCE=stdin)
+    // lê linhas de campos do tshark; senão captura ao vivo em `iface` ("br-lan,br-guest" para várias)
+    pub fn from_args(iface: &str) -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        if let Some(pos) = args.iter().position(|a| a == "--read" || a == "-r") {
+            if let Some(path) = args.get(pos + 1) {
+                return SourceSpec::PcapFile(path.clone());
+            }
+        }
+        if let Some(path) = std::env::var("PCAP_FILE").ok().filter(|p| !p.is_empty()) {
+            return SourceSpec::PcapFile(path);
+        }
+        if args.iter().any(|a| a == "--stdin") || std::env::var("CAPTURE_SOURCE").is_ok_and(|v| v == "stdin") {
+            return SourceSpec::TsharkStdin;
+        }
+        SourceSpec::Live(iface.split(',').map(|i| i.trim().to_string()).filter(|i| !i.is_empty()).collect())
+    }
+
+    pub fn open(&self) -> io::Result<Box<dyn PacketSource>> {
+        Ok(match self {
+            SourceSpec::Live(ifaces) if ifaces.len() == 1 => Box::new(LiveCapture::open(&ifaces[0])?),
+            SourceSpec::Live(ifaces) => Box::new(MultiCapture::open(ifaces)?),
+            SourceSpec::PcapFile(path) => Box::new(PcapFile::open(path)?),
+            SourceSpec::TsharkStdin => Box::new(TsharkLines::new(io::stdin().lock())),
+        })
+    }
+
+    // Interfaces capturadas ao vivo (vazio em replay/stdin)
+    pub fn ifaces(&self) -> &[String] {
+        match self {
+            SourceSpec::Live(ifaces) => ifaces,
+            _ => &[],
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SourceSpec::Live(ifaces) if ifaces.len() == 1 => format!("interface {}", ifaces[0]),
+            SourceSpec::Live(ifaces) => format!("interfaces {}", ifaces.join(", ")),
+            SourceSpec::PcapFile(path) => format!("arquivo {}", path),
+            SourceSpec::TsharkStdin => "stdin (campos do tshark)".to_string(),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// Contagem de bytes in/out por dispositivo da LAN (MAC quando conhecido, senão o IP),
+// com divisão por protocolo L4 e por porta de serviço
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::devices::DeviceTable;
+use crate::lan::{LanMatcher, Segments};
+use crate::packet::{Packet, PROTO_ARP, PROTO_ICMPV6, PROTO_TCP, PROTO_UDP};
+use crate::timeseries::Sample;
+
+pub const DEFAULT_SERVICE_PORTS: [u16; 4] = [53, 80, 123, 443];
+
+// Portas padrão mais as de SERVICE_PORTS ("22,853,8080")
+pub fn service_ports_from_env() -> BTreeSet<u16> {
+    let extra = std::env::var("SERVICE_PORTS").unwrap_or_default();
+    let mut ports: BTreeSet<u16> = DEFAULT_SERVICE_PORTS.into_iter().collect();
+    for p in extra.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        ports.insert(p.parse().unwrap_or_else(|_| panic!("SERVICE_PORTS: porta inválida '{}'", p)));
+    }
+    ports
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Traffic {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub bytes_total: u64,
+}
+
+impl Traffic {
+    fn add(&mut self, size: u64, outbound: bool) {
+        if outbound { self.bytes_out += size } else { self.bytes_in += size }
+        self.bytes_total += size;
+    }
+
+    fn merge(&mut self, other: &Traffic) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.bytes_total += other.bytes_total;
+    }
+}
+
+// Por protocolo ("tcp", "udp", "icmp", "other") e por porta de serviço ("443", ..., "other")
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Breakdown {
+    #[serde(default)]
+    pub protocols: BTreeMap<String, Traffic>,
+    #[serde(default)]
+    pub ports: BTreeMap<String, Traffic>,
+}
+
+impl Breakdown {
+    fn add(&mut self, protocol: &'static str, port: &str, size: u64, outbound: bool) {
+        self.protocols.entry(protocol.to_string()).or_default().add(size, outbound);
+        self.ports.entry(port.to_string()).or_default().add(size, outbound);
+    }
+
+    pub fn merge(&mut self, other: &Breakdown) {
+        for (k, t) in &other.protocols { self.protocols.entry(k.clone()).or_default().merge(t); }
+        for (k, t) in &other.ports { self.ports.entry(k.clone()).or_default().merge(t); }
+    }
+}
+
+// Quando os contadores zeram; o período fechado vira arquivo de histórico
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rollover {
+    Daily,
+    Monthly,
+    Never,
+}
+
+impl Rollover {
+    // BYTES_ROLLOVER: daily (padrão), monthly ou never
+    pub fn from_env() -> Self {
+        match std::env::var("BYTES_ROLLOVER").unwrap_or_default().trim() {
+            "" | "daily" => Rollover::Daily,
+            "monthly" => Rollover::Monthly,
+            "never" => Rollover::Never,
+            other => panic!("BYTES_ROLLOVER: valor inválido '{}' (daily, monthly ou never)", other),
+        }
+    }
+
+    // Período do instante, em hora local: "2025-12-01", "2025-12" ou "" (sem virada)
+    pub fn period_of(&self, ts: f64) -> String {
+        let local: DateTime<Local> = DateTime::from(DateTime::from_timestamp(ts as i64, 0).unwrap_or_default());
+        match self {
+            Rollover::Daily => local.format("%Y-%m-%d").to_string(),
+            Rollover::Monthly => local.format("%Y-%m").to_string(),
+            Rollover::Never => String::new(),
+        }
+    }
+}
+
+// traffic-bytes.json -> traffic-bytes-2025-12-01.json
+pub fn history_path(output: &str, period: &str) -> String {
+    let stem = output.strip_suffix(".json").unwrap_or(output);
+    format!("{}-{}.json", stem, period)
+}
+
+pub const RATE_WINDOWS: [(&str, i64); 3] = [("10s", 10), ("1m", 60), ("5m", 300)];
+const RATE_HISTORY: i64 = 300; // segundos guardados (a maior janela)
+
+// Bytes por segundo do relógio dos pacotes, para taxa em janela deslizante
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateWindow {
+    buckets: VecDeque<(i64, u64, u64)>, // (segundo, bytes in, bytes out)
+}
+
+impl RateWindow {
+    pub fn add(&mut self, ts: f64, size: u64, outbound: bool) {
+        let sec = ts.floor() as i64;
+        match self.buckets.back_mut() {
+            // Pacote fora de ordem (multi-interface) cai no segundo mais recente
+            Some(b) if b.0 >= sec => if outbound { b.2 += size } else { b.1 += size },
+            _ => self.buckets.push_back(if outbound { (sec, 0, size) } else { (sec, size, 0) }),
+        }
+        while self.buckets.front().is_some_and(|b| b.0 <= sec - RATE_HISTORY) {
+            self.buckets.pop_front();
+        }
+    }
+
+    // (bits/s in, bits/s out) nos últimos `window` segundos até `now`
+    pub fn bps(&self, now: f64, window: i64) -> (f64, f64) {
+        let now_sec = now.floor() as i64;
+        let (bytes_in, bytes_out) = self.buckets.iter()
+            .filter(|b| b.0 > now_sec - window && b.0 <= now_sec)
+            .fold((0, 0), |acc, b| (acc.0 + b.1, acc.1 + b.2));
+        (bytes_in as f64 * 8.0 / window as f64, bytes_out as f64 * 8.0 / window as f64)
+    }
+
+    fn merge(&mut self, other: &RateWindow) {
+        let mut all: BTreeMap<i64, (u64, u64)> = BTreeMap::new();
+        for b in self.buckets.iter().chain(&other.buckets) {
+            let e = all.entry(b.0).or_default();
+            e.0 += b.1;
+            e.1 += b.2;
+        }
+        self.buckets = all.into_iter().map(|(sec, (i, o))| (sec, i, o)).collect();
+    }
+}
+
+fn protocol_class(p: u8) -> &'static str {
+    match p {
+        PROTO_TCP => "tcp",
+        PROTO_UDP => "udp",
+        1 | PROTO_ICMPV6 => "icmp",
+        _ => "other",
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientData {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub bytes_total: u64,
+    pub last_seen_any: f64,
+    pub last_seen_out: f64,
+    pub iface: Option<String>, // segmento onde o cliente foi contado por último
+    pub ip: Option<String>,    // último IP do dispositivo
+    pub mac: Option<String>,
+    pub vendor: Option<String>,
+    pub breakdown: Breakdown,
+    pub packets_in: u64, // todos os pacotes IP, inclusive sem payload (ACK puro, SYN)
+    pub packets_out: u64,
+    pub rates: RateWindow,
+    pub interval: Sample, // desde o último take_interval (minuto do histórico)
+}
+
+impl ClientData {
+    // Junta o histórico de `other` (ex: contado pelo IP antes de o MAC ser conhecido)
+    pub fn merge(&mut self, other: ClientData) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.bytes_total += other.bytes_total;
+        self.last_seen_any = self.last_seen_any.max(other.last_seen_any);
+        self.last_seen_out = self.last_seen_out.max(other.last_seen_out);
+        if self.iface.is_none() { self.iface = other.iface; }
+        self.breakdown.merge(&other.breakdown);
+        self.packets_in += other.packets_in;
+        self.packets_out += other.packets_out;
+        self.rates.merge(&other.rates);
+        self.interval.merge(&other.interval);
+    }
+}
+
+pub struct ByteCounter {
+    lan: LanMatcher,
+    pub ignore_clients: HashSet<String>, // IPs ou MACs
+    pub devices: DeviceTable,
+    pub stats: HashMap<String, ClientData>, // chave: ID do dispositivo
+    pub service_ports: BTreeSet<u16>,
+    pub segments: Segments, // redes de cada interface, contra a contagem dupla de tráfego roteado
+    pub network: Breakdown, // rede toda: cada pacote uma vez, sentido visto da LAN
+}
+
+impl ByteCounter {
+    pub fn new(lan: LanMatcher) -> Self {
+        ByteCounter {
+            lan,
+            ignore_clients: HashSet::new(),
+            devices: DeviceTable::new(),
+            stats: HashMap::new(),
+            service_ports: DEFAULT_SERVICE_PORTS.into_iter().collect(),
+            segments: Segments::default(),
+            network: Breakdown::default(),
+        }
+    }
+
+    pub fn process(&mut self, pkt: &Packet) {
+        // IP que acabou de ganhar MAC: o que foi contado pelo IP passa para o dispositivo
+        for ip in self.devices.feed(pkt) {
+            if let Some(old) = self.stats.remove(&ip) {
+                let id = self.devices.device_id(&ip);
+                self.stats.entry(id).or_default().merge(old);
+            }
+        }
+        // ARP só alimenta a tabela de dispositivos; pacote IP sem payload (ACK, SYN) conta só como pacote
+        if pkt.protocol == PROTO_ARP { return; }
+        let size = pkt.len;
+        let now = pkt.ts;
+        let src_ip = pkt.src.to_string();
+        let dst_ip = pkt.dst.to_string();
+
+        let is_src = self.lan.is_lan(&pkt.src);
+        let is_dst = self.lan.is_lan(&pkt.dst);
+
+        if (is_src && self.is_ignored(&src_ip)) || (is_dst && self.is_ignored(&dst_ip)) { return; }
+
+        let protocol = protocol_class(pkt.protocol);
+        // Porta de serviço vista de cada lado; a rede toda usa a do lado da LAN que contou
+        let port_out = self.service_port(pkt.dst_port, pkt.src_port);
+        let port_in = self.service_port(pkt.src_port, pkt.dst_port);
+        let mut counted = None;
+        if is_src {
+            if let Some(e) = self.entry(src_ip, &pkt.src, pkt) {
+                e.bytes_out += size; e.bytes_total += size; e.last_seen_any = now; e.last_seen_out = now;
+                e.packets_out += 1;
+                e.interval.add(size, true);
+                if size > 0 {
+                    e.rates.add(now, size, true);
+                    e.breakdown.add(protocol, &port_out, size, true);
+                }
+                counted = Some((&port_out, true));
+            }
+        }
+        if is_dst {
+            if let Some(e) = self.entry(dst_ip, &pkt.dst, pkt) {
+                e.bytes_in += size; e.bytes_total += size; e.last_seen_any = now;
+                e.packets_in += 1;
+                e.interval.add(size, false);
+                if size > 0 {
+                    e.rates.add(now, size, false);
+                    e.breakdown.add(protocol, &port_in, size, false);
+                }
+                counted = counted.or(Some((&port_in, false)));
+            }
+        }
+        if let Some((port, outbound)) = counted.filter(|_| size > 0) {
+            self.network.add(protocol, port, size, outbound);
+        }
+    }
+
+    // Porta de serviço do ponto de vista do cliente: a do outro lado, ou a
+    // própria quando o cliente é o servidor (ex: NTP/DNS local)
+    fn service_port(&self, remote_port: u16, own_port: u16) -> String {
+        [remote_port, own_port].into_iter()
+            .find(|p| *p != 0 && self.service_ports.contains(p))
+            .map_or_else(|| "other".to_string(), |p| p.to_string())
+    }
+
+    // Com várias interfaces, tráfego roteado entre segmentos passa pelas duas bridges;
+    // só conta a cópia vista no segmento do próprio cliente (ver lan::Segments)
+    fn entry(&mut self, ip: String, addr: &IpAddr, pkt: &Packet) -> Option<&mut ClientData> {
+        if !self.segments.serves(pkt.iface.as_deref(), addr) { return None; }
+        let mac = self.devices.mac_of(&ip).map(str::to_string);
+        let vendor = self.devices.vendor_of(&ip).map(str::to_string);
+        let id = mac.clone().unwrap_or_else(|| ip.clone());
+        let e = self.stats.entry(id).or_default();
+        if let Some(seen) = pkt.iface.as_deref() { e.iface = Some(seen.to_string()); }
+        e.ip = Some(ip);
+        e.mac = mac;
+        e.vendor = vendor;
+        Some(e)
+    }
+
+    // Contagem de cada dispositivo desde a chamada anterior; zera para o próximo intervalo
+    pub fn take_interval(&mut self) -> HashMap<String, Sample> {
+        self.stats.iter_mut()
+            .filter(|(_, c)| c.interval != Sample::default())
+            .map(|(id, c)| (id.clone(), std::mem::take(&mut c.interval)))
+            .collect()
+    }
+
+    // Virada de período: zera as contagens, mantém os vínculos de dispositivo
+    pub fn reset(&mut self) {
+        self.stats.clear();
+        self.network = Breakdown::default();
+    }
+
+    fn is_ignored(&self, ip: &str) -> bool {
+        self.ignore_clients.contains(ip) || self.devices.mac_of(ip).is_some_and(|m| self.ignore_clients.contains(m))
+    }
+}
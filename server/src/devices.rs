@@ -0,0 +1,148 @@
+// Identidade de dispositivo por MAC: vínculos IP <-> MAC aprendidos de ARP,
+// NDP (IPv6) e DHCP, para o histórico não se partir quando o IP muda
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dhcp::{self, dhcp_payload, DHCP_ACK};
+use crate::oui::OuiDb;
+use crate::packet::{Packet, PROTO_ARP, PROTO_ICMPV6};
+use crate::{load_json_file, save_json_file};
+
+const NDP_NEIGHBOR_SOLICIT: u8 = 135;
+const NDP_NEIGHBOR_ADVERT: u8 = 136;
+const NDP_OPT_SOURCE_LL: u8 = 1;
+const NDP_OPT_TARGET_LL: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Binding {
+    pub mac: String,
+    pub last_seen: f64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub vendor: Option<String>, // fabricante pelo OUI, ou "randomized"
+}
+
+// Procura a opção de endereço de enlace (origem ou alvo) de uma mensagem NDP
+fn ndp_link_addr(msg: &[u8], wanted: u8) -> Option<String> {
+    let mut pos = 24; // tipo, código, checksum, flags/reservado, endereço alvo
+    while pos + 2 <= msg.len() {
+        let len = msg[pos + 1] as usize * 8;
+        if len == 0 { return None; }
+        if msg[pos] == wanted && len >= 8 {
+            return Some(dhcp::format_mac(msg.get(pos + 2..pos + 8)?));
+        }
+        pos += len;
+    }
+    None
+}
+
+// Vínculos (IP, MAC) que o pacote prova. Só mensagens que a própria máquina manda
+// na LAN: tráfego roteado carrega o MAC do roteador, não o do cliente.
+pub fn bindings(pkt: &Packet) -> Vec<(IpAddr, String)> {
+    let mut out = Vec::new();
+    match pkt.protocol {
+        PROTO_ARP if pkt.payload.len() >= 28 => {
+            // Sender 0.0.0.0 é sonda de conflito (RFC 5227), ainda sem IP
+            if !pkt.src.is_unspecified() {
+                out.push((pkt.src, dhcp::format_mac(&pkt.payload[8..14])));
+            }
+        }
+        PROTO_ICMPV6 => {
+            let msg = &pkt.payload;
+            match msg.first() {
+                Some(&NDP_NEIGHBOR_SOLICIT) if !pkt.src.is_unspecified() => {
+                    if let Some(mac) = ndp_link_addr(msg, NDP_OPT_SOURCE_LL) { out.push((pkt.src, mac)); }
+                }
+                Some(&NDP_NEIGHBOR_ADVERT) if msg.len() >= 24 => {
+                    let target: [u8; 16] = msg[8..24].try_into().unwrap_or_default();
+                    if let Some(mac) = ndp_link_addr(msg, NDP_OPT_TARGET_LL) { out.push((IpAddr::V6(Ipv6Addr::from(target)), mac)); }
+                }
+                _ => {}
+            }
+        }
+        _ => {
+            if let Some(msg) = dhcp_payload(pkt).and_then(dhcp::parse) {
+                if msg.msg_type == DHCP_ACK && !msg.from_client {
+                    if let Some(ip) = msg.yiaddr.or(msg.ciaddr) { out.push((IpAddr::V4(ip), msg.mac)); }
+                }
+            }
+        }
+    }
+    out
+}
+
+// IP -> MAC atual, persistido em JSON. O ID estável do dispositivo é o MAC;
+// sem vínculo conhecido (ex: fonte sem camada 2) o cliente continua sendo o IP.
+#[derive(Default)]
+pub struct DeviceTable {
+    pub bindings: BTreeMap<String, Binding>,
+    dirty: bool, // mudou desde o último save
+    oui: OuiDb,
+}
+
+impl DeviceTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &str) -> Self {
+        DeviceTable { bindings: load_json_file(path), ..Default::default() }
+    }
+
+    // Base de fabricantes; completa os vínculos já conhecidos que ainda não têm
+    pub fn set_oui(&mut self, oui: OuiDb) {
+        self.oui = oui;
+        for b in self.bindings.values_mut() {
+            if b.vendor.is_none() {
+                b.vendor = self.oui.label(&b.mac);
+                self.dirty |= b.vendor.is_some();
+            }
+        }
+    }
+
+    pub fn save(&mut self, path: &str) {
+        save_json_file(path, &self.bindings);
+        self.dirty = false;
+    }
+
+    // Vínculos mudam raramente; quem persiste chama isto no ciclo de recarga
+    pub fn save_if_dirty(&mut self, path: &str) {
+        if self.dirty { self.save(path); }
+    }
+
+    // IPs que passaram a apontar para outro MAC (ou foram vistos pela primeira vez)
+    pub fn feed(&mut self, pkt: &Packet) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (ip, mac) in bindings(pkt) {
+            let ip = ip.to_string();
+            let b = self.bindings.entry(ip.clone()).or_default();
+            if b.mac != mac {
+                b.vendor = self.oui.label(&mac);
+                b.mac = mac;
+                changed.push(ip);
+                self.dirty = true;
+            }
+            b.last_seen = pkt.ts;
+        }
+        changed
+    }
+
+    pub fn mac_of(&self, ip: &str) -> Option<&str> {
+        self.bindings.get(ip).map(|b| b.mac.as_str())
+    }
+
+    pub fn vendor_of(&self, ip: &str) -> Option<&str> {
+        self.bindings.get(ip).and_then(|b| b.vendor.as_deref())
+    }
+
+    pub fn device_id(&self, ip: &str) -> String {
+        self.mac_of(ip).unwrap_or(ip).to_string()
+    }
+
+    // IPs vinculados hoje a esse MAC (v4 e v6)
+    pub fn ips_of(&self, mac: &str) -> Vec<String> {
+        self.bindings.iter().filter(|(_, b)| b.mac == mac).map(|(ip, _)| ip.clone()).collect()
+    }
+}
@@ -0,0 +1,170 @@
+// DHCP snooping: aprende hostname (opção 12), vendor class (opção 60), MAC e
+// IP concedido a partir dos DISCOVER/REQUEST/ACK que passam pela interface
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::packet::{Packet, PROTO_UDP};
+use crate::{load_json_file, save_json_file};
+
+pub const DHCP_DISCOVER: u8 = 1;
+pub const DHCP_REQUEST: u8 = 3;
+pub const DHCP_ACK: u8 = 5;
+pub const DHCP_INFORM: u8 = 8;
+
+const OP_REQUEST: u8 = 1;
+const MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+const OPTIONS_OFFSET: usize = 240;
+
+const OPT_PAD: u8 = 0;
+const OPT_HOSTNAME: u8 = 12;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_MSG_TYPE: u8 = 53;
+const OPT_PARAM_LIST: u8 = 55;
+const OPT_VENDOR_CLASS: u8 = 60;
+const OPT_END: u8 = 255;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DhcpMessage {
+    pub from_client: bool, // op=1 (BOOTREQUEST)
+    pub msg_type: u8,
+    pub mac: String,       // chaddr, "aa:bb:cc:dd:ee:ff"
+    pub ciaddr: Option<Ipv4Addr>,
+    pub yiaddr: Option<Ipv4Addr>,
+    pub requested_ip: Option<Ipv4Addr>,
+    pub hostname: Option<String>,
+    pub vendor_class: Option<String>,
+    pub param_list: Vec<u8>, // opção 55: a ordem varia por sistema (ver fingerprint.rs)
+}
+
+fn nonzero_ip(b: &[u8]) -> Option<Ipv4Addr> {
+    let ip = Ipv4Addr::new(b[0], b[1], b[2], b[3]);
+    if ip.is_unspecified() { None } else { Some(ip) }
+}
+
+// Alguns clientes mandam o nome com \0 no fim ou caracteres de controle
+fn clean_text(data: &[u8]) -> Option<String> {
+    let s: String = String::from_utf8_lossy(data).chars().filter(|c| !c.is_control()).collect();
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+pub fn format_mac(b: &[u8]) -> String {
+    b.iter().map(|x| format!("{:02x}", x)).collect::<Vec<_>>().join(":")
+}
+
+// Payload UDP de uma mensagem BOOTP/DHCP
+pub fn parse(data: &[u8]) -> Option<DhcpMessage> {
+    if data.len() < OPTIONS_OFFSET || data[236..240] != MAGIC_COOKIE { return None; }
+    let hlen = data[2] as usize;
+    if hlen == 0 || hlen > 16 { return None; }
+    let mut msg = DhcpMessage {
+        from_client: data[0] == OP_REQUEST,
+        mac: format_mac(&data[28..28 + hlen]),
+        ciaddr: nonzero_ip(&data[12..16]),
+        yiaddr: nonzero_ip(&data[16..20]),
+        ..Default::default()
+    };
+
+    let mut pos = OPTIONS_OFFSET;
+    while pos < data.len() {
+        let code = data[pos];
+        if code == OPT_END { break; }
+        if code == OPT_PAD { pos += 1; continue; }
+        let len = *data.get(pos + 1)? as usize;
+        let value = data.get(pos + 2..pos + 2 + len)?;
+        match code {
+            OPT_MSG_TYPE => msg.msg_type = *value.first()?,
+            OPT_HOSTNAME => msg.hostname = clean_text(value),
+            OPT_VENDOR_CLASS => msg.vendor_class = clean_text(value),
+            OPT_PARAM_LIST => msg.param_list = value.to_vec(),
+            OPT_REQUESTED_IP if len == 4 => msg.requested_ip = nonzero_ip(value),
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+    if msg.msg_type == 0 { return None; } // BOOTP puro, sem opção 53
+    Some(msg)
+}
+
+pub fn dhcp_payload(pkt: &Packet) -> Option<&[u8]> {
+    if pkt.protocol != PROTO_UDP { return None; }
+    let ports = [pkt.src_port, pkt.dst_port];
+    if ports.contains(&67) && ports.contains(&68) { Some(&pkt.payload) } else { None }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Lease {
+    pub mac: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_class: Option<String>,
+    pub last_seen: f64,
+}
+
+// Tabela de concessões por MAC, persistida em JSON
+#[derive(Default)]
+pub struct LeaseTable {
+    pub leases: BTreeMap<String, Lease>,
+}
+
+impl LeaseTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &str) -> Self {
+        LeaseTable { leases: load_json_file(path) }
+    }
+
+    pub fn save(&self, path: &str) {
+        save_json_file(path, &self.leases);
+    }
+
+    // Devolve true se a tabela mudou (hora de persistir)
+    pub fn feed(&mut self, pkt: &Packet) -> bool {
+        match dhcp_payload(pkt).and_then(parse) {
+            Some(msg) => self.learn(&msg, pkt.ts),
+            None => false,
+        }
+    }
+
+    pub fn learn(&mut self, msg: &DhcpMessage, ts: f64) -> bool {
+        let ip = match msg.msg_type {
+            // Só o ACK confirma o IP; do lado do cliente vale o ciaddr (renovação/INFORM)
+            DHCP_ACK if !msg.from_client => msg.yiaddr.or(msg.ciaddr),
+            DHCP_REQUEST | DHCP_INFORM if msg.from_client => msg.ciaddr,
+            DHCP_DISCOVER | DHCP_REQUEST | DHCP_INFORM | DHCP_ACK => None,
+            _ => return false,
+        };
+        let lease = self.leases.entry(msg.mac.clone()).or_insert_with(|| Lease { mac: msg.mac.clone(), ..Default::default() });
+        let before = lease.clone();
+        if let Some(ip) = ip { lease.ip = Some(ip.to_string()); }
+        // O nome do cliente tem prioridade sobre o que o servidor devolve no ACK
+        if let Some(h) = &msg.hostname {
+            if msg.from_client || lease.hostname.is_none() { lease.hostname = Some(h.clone()); }
+        }
+        if msg.from_client && msg.vendor_class.is_some() { lease.vendor_class = msg.vendor_class.clone(); }
+        let changed = *lease != before;
+        lease.last_seen = ts;
+        changed
+    }
+
+    // IP -> hostname, para o fallback de resolve_client_name. Com o mesmo IP
+    // em mais de um MAC (concessão que trocou de dono), vale a mais recente.
+    pub fn hostnames(&self) -> HashMap<String, String> {
+        let mut latest: HashMap<String, &Lease> = HashMap::new();
+        for lease in self.leases.values() {
+            if let (Some(ip), Some(_)) = (&lease.ip, &lease.hostname) {
+                let slot = latest.entry(ip.clone()).or_insert(lease);
+                if lease.last_seen > slot.last_seen { *slot = lease; }
+            }
+        }
+        latest.into_iter().map(|(ip, l)| (ip, l.hostname.clone().unwrap_or_default())).collect()
+    }
+}
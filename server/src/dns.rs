@@ -0,0 +1,252 @@
+// Parser do formato de mensagem DNS (RFC 1035), com as respostas que interessam
+// para saber para onde o cliente vai de fato: A, AAAA, CNAME e HTTPS/SVCB.
+// Também serve para mDNS/LLMNR, que usam o mesmo formato (ver names.rs).
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_CNAME: u16 = 5;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SVCB: u16 = 64;
+pub const TYPE_HTTPS: u16 = 65;
+
+pub const RCODE_NOERROR: u8 = 0;
+pub const RCODE_SERVFAIL: u8 = 2;
+pub const RCODE_NXDOMAIN: u8 = 3;
+pub const RCODE_REFUSED: u8 = 5;
+
+// Nome do rcode como aparece no log (ex: NXDOMAIN); desconhecido sai como número
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        RCODE_NOERROR => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        RCODE_SERVFAIL => "SERVFAIL".to_string(),
+        RCODE_NXDOMAIN => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        RCODE_REFUSED => "REFUSED".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Chaves de parâmetros SVCB (RFC 9460)
+const SVC_ALPN: u16 = 1;
+const SVC_IPV4HINT: u16 = 4;
+const SVC_IPV6HINT: u16 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Ptr(String),
+    Svcb { priority: u16, target: String, alpn: Vec<String>, ipv4_hints: Vec<Ipv4Addr>, ipv6_hints: Vec<Ipv6Addr> },
+    Other(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsMessage {
+    pub id: u16,
+    pub is_response: bool,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub additional: Vec<Record>, // authority + additional (o mDNS manda A/AAAA aqui)
+}
+
+fn be16(data: &[u8], off: usize) -> Option<u16> {
+    data.get(off..off + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be32(data: &[u8], off: usize) -> Option<u32> {
+    data.get(off..off + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Lê um nome DNS a partir de `off`, seguindo ponteiros de compressão.
+// Retorna o nome e o offset logo após ele na mensagem original.
+pub fn read_name(msg: &[u8], off: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = off;
+    let mut next = None;
+    let mut jumps = 0;
+    loop {
+        let len = *msg.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xc0 == 0xc0 {
+            let ptr = ((len & 0x3f) << 8) | *msg.get(pos + 1)? as usize;
+            if next.is_none() { next = Some(pos + 2); }
+            jumps += 1;
+            if jumps > 16 { return None; }
+            pos = ptr;
+            continue;
+        }
+        let label = msg.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    Some((labels.join("."), next.unwrap_or(pos)))
+}
+
+fn read_record(msg: &[u8], pos: usize) -> Option<(Record, usize)> {
+    let (name, next) = read_name(msg, pos)?;
+    let rtype = be16(msg, next)?;
+    let ttl = be32(msg, next + 4)?;
+    let rdlen = be16(msg, next + 8)? as usize;
+    let rdata_off = next + 10;
+    let rdata = msg.get(rdata_off..rdata_off + rdlen)?;
+    let data = parse_rdata(msg, rtype, rdata_off, rdata)?;
+    Some((Record { name, rtype, ttl, data }, rdata_off + rdlen))
+}
+
+// Decodifica cabeçalho, perguntas e a seção de respostas. Authority/additional
+// são lidas na medida do possível: um registro quebrado ali não invalida a mensagem.
+pub fn parse(msg: &[u8]) -> Option<DnsMessage> {
+    if msg.len() < 12 { return None; }
+    let id = be16(msg, 0)?;
+    let flags = be16(msg, 2)?;
+    let qdcount = be16(msg, 4)?;
+    let ancount = be16(msg, 6)?;
+    let extra = be16(msg, 8)? as usize + be16(msg, 10)? as usize;
+
+    let mut pos = 12;
+    let mut questions = Vec::new();
+    for _ in 0..qdcount {
+        let (name, next) = read_name(msg, pos)?;
+        questions.push(Question { name, qtype: be16(msg, next)? });
+        pos = next + 4;
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..ancount {
+        let (record, next) = read_record(msg, pos)?;
+        answers.push(record);
+        pos = next;
+    }
+
+    let mut additional = Vec::new();
+    for _ in 0..extra {
+        match read_record(msg, pos) {
+            Some((record, next)) => { additional.push(record); pos = next; }
+            None => break,
+        }
+    }
+
+    Some(DnsMessage { id, is_response: flags & 0x8000 != 0, rcode: (flags & 0x000f) as u8, questions, answers, additional })
+}
+
+fn parse_rdata(msg: &[u8], rtype: u16, off: usize, rdata: &[u8]) -> Option<RecordData> {
+    Some(match rtype {
+        TYPE_A if rdata.len() == 4 => RecordData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+        TYPE_AAAA if rdata.len() == 16 => {
+            let octets: [u8; 16] = rdata.try_into().ok()?;
+            RecordData::Aaaa(Ipv6Addr::from(octets))
+        }
+        // CNAME pode usar compressão apontando para o resto da mensagem
+        TYPE_CNAME => RecordData::Cname(read_name(msg, off)?.0),
+        TYPE_PTR => RecordData::Ptr(read_name(msg, off)?.0),
+        TYPE_SVCB | TYPE_HTTPS => parse_svcb(rdata)?,
+        _ => RecordData::Other(rdata.to_vec()),
+    })
+}
+
+// SVCB/HTTPS: o alvo não usa compressão, então basta o próprio rdata
+fn parse_svcb(rdata: &[u8]) -> Option<RecordData> {
+    let priority = be16(rdata, 0)?;
+    let (target, mut pos) = read_name(rdata, 2)?;
+    let mut alpn = Vec::new();
+    let mut ipv4_hints = Vec::new();
+    let mut ipv6_hints = Vec::new();
+
+    while pos + 4 <= rdata.len() {
+        let key = be16(rdata, pos)?;
+        let len = be16(rdata, pos + 2)? as usize;
+        let value = rdata.get(pos + 4..pos + 4 + len)?;
+        match key {
+            SVC_ALPN => {
+                let mut i = 0;
+                while i < value.len() {
+                    let l = value[i] as usize;
+                    alpn.push(String::from_utf8_lossy(value.get(i + 1..i + 1 + l)?).into_owned());
+                    i += 1 + l;
+                }
+            }
+            SVC_IPV4HINT => ipv4_hints.extend(value.chunks_exact(4).map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))),
+            SVC_IPV6HINT => ipv6_hints.extend(value.chunks_exact(16).filter_map(|c| <[u8; 16]>::try_from(c).ok()).map(Ipv6Addr::from)),
+            _ => {}
+        }
+        pos += 4 + len;
+    }
+    Some(RecordData::Svcb { priority, target, alpn, ipv4_hints, ipv6_hints })
+}
+
+impl DnsMessage {
+    pub fn query_name(&self) -> Option<&str> {
+        self.questions.first().map(|q| q.name.as_str()).filter(|n| !n.is_empty())
+    }
+
+    // Endereços para onde o nome resolve: A/AAAA da cadeia de CNAMEs
+    // e, na falta deles, os hints de registros HTTPS/SVCB
+    pub fn resolved_ips(&self) -> Vec<IpAddr> {
+        let mut names: Vec<String> = self.query_name().map(|n| vec![n.to_ascii_lowercase()]).unwrap_or_default();
+        // Segue CNAMEs (a ordem na resposta normalmente já é a da cadeia)
+        for rec in &self.answers {
+            if let RecordData::Cname(target) = &rec.data {
+                if names.contains(&rec.name.to_ascii_lowercase()) {
+                    names.push(target.to_ascii_lowercase());
+                }
+            }
+        }
+
+        let mut ips: Vec<IpAddr> = Vec::new();
+        for rec in self.answers.iter().filter(|r| names.contains(&r.name.to_ascii_lowercase())) {
+            let ip = match rec.data {
+                RecordData::A(v4) => IpAddr::V4(v4),
+                RecordData::Aaaa(v6) => IpAddr::V6(v6),
+                _ => continue,
+            };
+            if !ips.contains(&ip) { ips.push(ip); }
+        }
+        if ips.is_empty() {
+            for rec in &self.answers {
+                if let RecordData::Svcb { ipv4_hints, ipv6_hints, .. } = &rec.data {
+                    let hints = ipv4_hints.iter().map(|v4| IpAddr::V4(*v4)).chain(ipv6_hints.iter().map(|v6| IpAddr::V6(*v6)));
+                    for ip in hints {
+                        if !ips.contains(&ip) { ips.push(ip); }
+                    }
+                }
+            }
+        }
+        ips
+    }
+
+    // Menor TTL entre as respostas (quanto tempo a resolução vale no cliente)
+    pub fn min_ttl(&self) -> Option<u32> {
+        self.answers.iter().map(|r| r.ttl).min()
+    }
+}
+
+// Retorna o primeiro nome da seção de perguntas (vale para query e resposta)
+pub fn query_name(msg: &[u8]) -> Option<String> {
+    if msg.len() < 12 { return None; }
+    let qdcount = u16::from_be_bytes([msg[4], msg[5]]);
+    if qdcount == 0 { return None; }
+    let (name, _) = read_name(msg, 12)?;
+    if name.is_empty() { None } else { Some(name) }
+}
@@ -0,0 +1,250 @@
+// Eventos de domínio num SQLite embutido, com índices por tempo, cliente, MAC e
+// domínio: a API filtra e pagina direto no banco, de um ou vários dias, sem
+// ler o log inteiro. O domains grava em lotes (uma transação por lote).
+
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OpenFlags, Row};
+
+use crate::events::{LogEvent, EVENT_VERSION, FLAG_VIA_DNS};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    ts REAL NOT NULL,
+    client TEXT NOT NULL,
+    name TEXT,
+    mac TEXT,
+    domain TEXT NOT NULL,
+    remote TEXT NOT NULL,
+    source TEXT NOT NULL,
+    iface TEXT,
+    alpn TEXT,
+    tls TEXT,
+    ja3 TEXT,
+    ja4 TEXT,
+    delayed_dns INTEGER NOT NULL DEFAULT 0,
+    answers TEXT,
+    ttl INTEGER,
+    rcode TEXT,
+    rdomain TEXT -- domínio com os rótulos invertidos (com.example.www), para busca por sufixo no índice
+);
+CREATE INDEX IF NOT EXISTS events_ts ON events(ts);
+CREATE INDEX IF NOT EXISTS events_client_ts ON events(client, ts);
+CREATE INDEX IF NOT EXISTS events_mac_ts ON events(mac, ts);
+";
+
+// Depois da migração das colunas (banco antigo ainda não tem rdomain)
+const DOMAIN_INDEX: &str = "
+DROP INDEX IF EXISTS events_domain_ts;
+CREATE INDEX IF NOT EXISTS events_rdomain_ts ON events(rdomain, ts);
+";
+
+const COLUMNS: &str = "ts, client, name, mac, domain, remote, source, iface, alpn, tls, ja3, ja4, delayed_dns, answers, ttl, rcode";
+
+// Colunas que entraram depois da primeira versão do schema (banco antigo ganha na abertura)
+const ADDED_COLUMNS: [(&str, &str); 4] = [("answers", "TEXT"), ("ttl", "INTEGER"), ("rcode", "TEXT"), ("rdomain", "TEXT")];
+
+// www.example.com -> com.example.www: os subdomínios de example.com ficam todos sob o prefixo "com.example."
+pub fn reversed_domain(domain: &str) -> String {
+    domain.to_ascii_lowercase().rsplit('.').collect::<Vec<_>>().join(".")
+}
+
+// Filtros do /logs; vazio = sem filtro
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub since: Option<f64>,
+    pub until: Option<f64>, // exclusivo
+    // Dispositivo: linha com MAC decide pelo MAC; sem MAC, por qualquer dos IPs
+    pub mac: Option<String>,
+    pub ips: Vec<String>,
+    pub domain: Option<String>, // o domínio e seus subdomínios
+    pub iface: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+pub struct EventDb {
+    conn: Connection,
+}
+
+impl EventDb {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        if let Some(parent) = Path::new(path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let conn = Connection::open(path)?;
+        // WAL: a API lê enquanto o domains grava
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)?;
+        for (column, kind) in ADDED_COLUMNS {
+            let exists: bool = conn.query_row("SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = ?1", [column], |r| r.get(0))?;
+            if !exists { conn.execute_batch(&format!("ALTER TABLE events ADD COLUMN {} {}", column, kind))?; }
+        }
+        let mut db = EventDb { conn };
+        db.fill_reversed_domains()?;
+        db.conn.execute_batch(DOMAIN_INDEX)?;
+        Ok(db)
+    }
+
+    // Linhas gravadas antes da coluna rdomain (uma vez, na primeira abertura após a atualização)
+    fn fill_reversed_domains(&mut self) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut select = tx.prepare("SELECT id, domain FROM events WHERE rdomain IS NULL")?;
+            let rows: Vec<(i64, String)> = select.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+            let mut update = tx.prepare("UPDATE events SET rdomain = ?1 WHERE id = ?2")?;
+            for (id, domain) in rows {
+                update.execute(rusqlite::params![reversed_domain(&domain), id])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // Só leitura (API); falha se o banco ainda não existe
+    pub fn open_read_only(path: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        conn.busy_timeout(std::time::Duration::from_secs(2))?;
+        Ok(EventDb { conn })
+    }
+
+    pub fn insert_batch(&mut self, events: &[LogEvent]) -> rusqlite::Result<()> {
+        if events.is_empty() { return Ok(()); }
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&format!("INSERT INTO events ({}, rdomain) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)", COLUMNS))?;
+            for ev in events {
+                stmt.execute(rusqlite::params![
+                    ev.ts, ev.client, ev.name, ev.mac.as_deref().map(str::to_ascii_lowercase), ev.domain, ev.remote, ev.source, ev.iface,
+                    ev.alpn, ev.tls, ev.ja3, ev.ja4, ev.flags.iter().any(|f| f == FLAG_VIA_DNS),
+                    (!ev.answers.is_empty()).then(|| ev.answers.join(",")), ev.ttl, ev.rcode, reversed_domain(&ev.domain),
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // Mais recentes primeiro
+    pub fn query(&self, q: &EventQuery) -> rusqlite::Result<Vec<LogEvent>> {
+        let mut cond: Vec<String> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+        if let Some(since) = q.since {
+            cond.push("ts >= ?".to_string());
+            args.push(Value::Real(since));
+        }
+        if let Some(until) = q.until {
+            cond.push("ts < ?".to_string());
+            args.push(Value::Real(until));
+        }
+        // MAC gravado em minúsculas: compara com `=` e usa o índice events_mac_ts
+        if q.mac.is_some() || !q.ips.is_empty() {
+            let ips = vec!["?"; q.ips.len()].join(", ");
+            let by_ip = format!("client IN ({})", ips);
+            match &q.mac {
+                Some(mac) if q.ips.is_empty() => {
+                    cond.push("mac = ?".to_string());
+                    args.push(Value::Text(mac.to_ascii_lowercase()));
+                }
+                Some(mac) => {
+                    cond.push(format!("(mac = ? OR (mac IS NULL AND {}))", by_ip));
+                    args.push(Value::Text(mac.to_ascii_lowercase()));
+                }
+                None => cond.push(by_ip),
+            }
+            args.extend(q.ips.iter().cloned().map(Value::Text));
+        }
+        if let Some(domain) = &q.domain {
+            // O domínio ou qualquer rdomain com prefixo "com.example.": faixa ['com.example.', 'com.example/')
+            // no índice events_rdomain_ts ('/' vem logo depois de '.')
+            let rev = reversed_domain(domain);
+            cond.push("(rdomain = ? OR (rdomain >= ? AND rdomain < ?))".to_string());
+            args.push(Value::Text(rev.clone()));
+            args.push(Value::Text(format!("{}.", rev)));
+            args.push(Value::Text(format!("{}/", rev)));
+        }
+        if let Some(iface) = &q.iface {
+            cond.push("iface = ?".to_string());
+            args.push(Value::Text(iface.clone()));
+        }
+        let filter = if cond.is_empty() { String::new() } else { format!("WHERE {}", cond.join(" AND ")) };
+        let sql = format!("SELECT {} FROM events {} ORDER BY ts DESC, id DESC LIMIT {} OFFSET {}", COLUMNS, filter, q.limit, q.offset);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), row_event)?;
+        rows.collect()
+    }
+
+    // Instante do evento mais antigo (None com o banco vazio)
+    pub fn first_ts(&self) -> rusqlite::Result<Option<f64>> {
+        self.conn.query_row("SELECT MIN(ts) FROM events", [], |r| r.get(0))
+    }
+
+    // Retenção: apaga os eventos anteriores a `before`. Devolve quantos saíram.
+    pub fn prune(&self, before: f64) -> rusqlite::Result<usize> {
+        self.conn.execute("DELETE FROM events WHERE ts < ?1", [before])
+    }
+}
+
+fn row_event(r: &Row) -> rusqlite::Result<LogEvent> {
+    let delayed: bool = r.get(12)?;
+    let answers: Option<String> = r.get(13)?;
+    Ok(LogEvent {
+        v: EVENT_VERSION,
+        ts: r.get(0)?,
+        client: r.get(1)?,
+        name: r.get(2)?,
+        mac: r.get(3)?,
+        domain: r.get(4)?,
+        remote: r.get(5)?,
+        source: r.get(6)?,
+        iface: r.get(7)?,
+        alpn: r.get(8)?,
+        tls: r.get(9)?,
+        ja3: r.get(10)?,
+        ja4: r.get(11)?,
+        answers: answers.map(|a| a.split(',').map(str::to_string).collect()).unwrap_or_default(),
+        ttl: r.get(14)?,
+        rcode: r.get(15)?,
+        flags: if delayed { vec![FLAG_VIA_DNS.to_string()] } else { Vec::new() },
+    })
+}
+
+pub const BATCH_SIZE: usize = 500;
+pub const BATCH_INTERVAL: f64 = 2.0; // segundos do relógio dos pacotes
+
+// Acumula eventos e grava um lote a cada BATCH_SIZE eventos ou BATCH_INTERVAL.
+// Lote que falha é descartado (o log texto/JSONL continua com os eventos).
+pub struct BatchWriter {
+    db: EventDb,
+    pending: Vec<LogEvent>,
+    last_flush: f64,
+}
+
+impl BatchWriter {
+    pub fn new(db: EventDb) -> Self {
+        BatchWriter { db, pending: Vec::new(), last_flush: 0.0 }
+    }
+
+    pub fn push(&mut self, ev: LogEvent) -> rusqlite::Result<()> {
+        self.pending.push(ev);
+        if self.pending.len() >= BATCH_SIZE { return self.flush(); }
+        Ok(())
+    }
+
+    pub fn flush_if_due(&mut self, now: f64) -> rusqlite::Result<()> {
+        if now - self.last_flush < BATCH_INTERVAL { return Ok(()); }
+        self.last_flush = now;
+        self.flush()
+    }
+
+    pub fn flush(&mut self) -> rusqlite::Result<()> {
+        let result = self.db.insert_batch(&self.pending);
+        self.pending.clear();
+        result
+    }
+
+    pub fn prune(&self, before: f64) -> rusqlite::Result<usize> {
+        self.db.prune(before)
+    }
+}
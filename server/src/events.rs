@@ -0,0 +1,158 @@
+// Log de domínios em JSON Lines: um objeto por linha, com versão e campos
+// tipados, lido sem o regex do formato texto. O texto continua disponível
+// (LOG_FORMAT) para quem acompanha o log com tail/grep.
+
+use serde::{Deserialize, Serialize};
+
+use crate::logline::{format_timestamp, LogLine};
+use crate::pipeline::DomainEvent;
+use crate::ParsedLogEntry;
+
+pub const EVENT_VERSION: u32 = 1;
+
+pub const FLAG_VIA_DNS: &str = "via_dns"; // DNS que expirou sem conexão TLS/HTTP correspondente
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Jsonl,
+    Both,
+}
+
+impl LogFormat {
+    // LOG_FORMAT: text (padrão), jsonl ou both
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT").unwrap_or_default().trim() {
+            "" | "text" => LogFormat::Text,
+            "jsonl" => LogFormat::Jsonl,
+            "both" => LogFormat::Both,
+            other => panic!("LOG_FORMAT: valor inválido '{}' (text, jsonl ou both)", other),
+        }
+    }
+
+    pub fn text(&self) -> bool {
+        matches!(self, LogFormat::Text | LogFormat::Both)
+    }
+
+    pub fn jsonl(&self) -> bool {
+        matches!(self, LogFormat::Jsonl | LogFormat::Both)
+    }
+}
+
+// traffic-domains.log -> traffic-domains.jsonl (padrão de LOG_JSONL_FILE)
+pub fn jsonl_path(log_file: &str) -> String {
+    format!("{}.jsonl", log_file.strip_suffix(".log").unwrap_or(log_file))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub v: u32,
+    pub ts: f64, // epoch do pacote
+    pub client: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>, // nome do dispositivo, se conhecido
+    pub domain: String,
+    pub remote: String,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub iface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub alpn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tls: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ja3: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ja4: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub answers: Vec<String>, // IPs resolvidos (DNS com resposta vista)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rcode: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub flags: Vec<String>,
+}
+
+impl LogEvent {
+    pub fn from_event(ev: &DomainEvent, name: Option<&str>) -> Self {
+        LogEvent {
+            v: EVENT_VERSION,
+            ts: ev.ts,
+            client: ev.client.clone(),
+            name: name.map(str::to_string),
+            domain: ev.domain.clone(),
+            remote: ev.remote.clone(),
+            source: ev.source.to_string(),
+            iface: ev.iface.clone(),
+            mac: ev.mac.clone(),
+            alpn: ev.alpn.clone(),
+            tls: ev.tls_version.clone(),
+            ja3: ev.ja3.clone(),
+            ja4: ev.ja4.clone(),
+            answers: ev.answers.iter().map(|ip| ip.to_string()).collect(),
+            ttl: ev.dns_ttl,
+            rcode: ev.dns_rcode.clone(),
+            flags: if ev.delayed { vec![FLAG_VIA_DNS.to_string()] } else { Vec::new() },
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // A linha que o formato texto teria gravado para este evento
+    pub fn to_log_line(&self) -> LogLine {
+        LogLine {
+            timestamp: format_timestamp(self.ts),
+            client_ip: self.client.clone(),
+            client_name: self.name.clone().unwrap_or_else(|| self.client.clone()),
+            domain: self.domain.clone(),
+            remote_ip: self.remote.clone(),
+            source: self.source.clone(),
+            delayed_dns: self.flags.iter().any(|f| f == FLAG_VIA_DNS),
+            alpn: self.alpn.clone(),
+            tls_version: self.tls.clone(),
+            ja3: self.ja3.clone(),
+            ja4: self.ja4.clone(),
+            iface: self.iface.clone(),
+            mac: self.mac.clone(),
+            answers: self.answers.clone(),
+            dns_ttl: self.ttl,
+            dns_rcode: self.rcode.clone(),
+        }
+    }
+
+    // Mesma forma que sai do formato texto, para a API tratar os dois igual;
+    // `raw` é sempre a linha texto, venha o evento do JSONL ou do SQLite
+    pub fn to_entry(&self) -> ParsedLogEntry {
+        ParsedLogEntry {
+            timestamp: format_timestamp(self.ts),
+            client_ip: self.client.clone(),
+            client_name: self.name.clone().unwrap_or_else(|| self.client.clone()),
+            host: self.domain.clone(),
+            domain: self.domain.clone(),
+            remote_ip: self.remote.clone(),
+            source: self.source.clone(),
+            alpn: self.alpn.clone(),
+            tls_version: self.tls.clone(),
+            ja3: self.ja3.clone(),
+            ja4: self.ja4.clone(),
+            iface: self.iface.clone(),
+            mac: self.mac.clone(),
+            delayed_dns: self.flags.iter().any(|f| f == FLAG_VIA_DNS),
+            answers: self.answers.clone(),
+            dns_ttl: self.ttl,
+            dns_rcode: self.rcode.clone(),
+            flags: self.flags.clone(),
+            raw: self.to_log_line().format(),
+        }
+    }
+}
+
+// Linha JSONL; versão mais nova que esta é aceita se os campos conhecidos baterem
+pub fn parse_event(line: &str) -> Option<LogEvent> {
+    serde_json::from_str(line.trim()).ok()
+}
@@ -0,0 +1,239 @@
+// Fingerprint passivo de sistema/tipo de aparelho: SYN do TCP (TTL, janela,
+// ordem das opções), lista de parâmetros do DHCP (opção 55) e User-Agent HTTP.
+// É palpite: o User-Agent vence o DHCP, que vence o SYN.
+// Guarda também os JA3/JA4 vistos por dispositivo (quais aplicações falam TLS).
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::devices::DeviceTable;
+use crate::dhcp::{self, dhcp_payload};
+use crate::http;
+use crate::lan::LanMatcher;
+use crate::packet::{Packet, SynInfo, PROTO_TCP};
+use crate::{load_json_file, save_json_file};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Guess {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub os: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub device: Option<String>, // celular, tablet, computador, tv, console, rede
+}
+
+fn guess(os: &str, device: Option<&str>) -> Guess {
+    Guess { os: Some(os.to_string()), device: device.map(str::to_string) }
+}
+
+// TTL inicial mais provável (o pacote chega com alguns saltos a menos)
+fn initial_ttl(ttl: u8) -> u8 {
+    match ttl {
+        0..=64 => 64,
+        65..=128 => 128,
+        _ => 255,
+    }
+}
+
+// "64:65535:mss,nop,ws,..." — assinatura guardada junto com o palpite
+pub fn syn_signature(syn: &SynInfo) -> String {
+    format!("{}:{}:{}", initial_ttl(syn.ttl), syn.window, syn.options)
+}
+
+pub fn guess_from_syn(syn: &SynInfo) -> Option<Guess> {
+    match initial_ttl(syn.ttl) {
+        128 => Some(guess("Windows", Some("computador"))),
+        255 => Some(guess("Embarcado", Some("rede"))),
+        _ if syn.options.starts_with("mss,nop,ws,nop,nop,ts,sok,eol") => Some(guess("macOS/iOS", None)),
+        _ if syn.options.starts_with("mss,sok,ts,nop,ws") => Some(guess("Linux/Android", None)),
+        _ => None,
+    }
+}
+
+pub fn guess_from_dhcp(params: &[u8]) -> Option<Guess> {
+    if params.is_empty() { return None; }
+    if params.contains(&249) && params.contains(&252) {
+        return Some(guess("Windows", Some("computador")));
+    }
+    if params.starts_with(&[1, 121, 3, 6, 15, 119, 252]) {
+        // macOS pede também LDAP/NetBIOS; iPhone/iPad não
+        return Some(if params.contains(&95) || params.contains(&44) { guess("macOS", Some("computador")) } else { guess("iOS", Some("celular")) });
+    }
+    if params.starts_with(&[1, 3, 6, 15, 26, 28, 51, 58, 59]) {
+        return Some(guess("Android", Some("celular")));
+    }
+    if params.starts_with(&[1, 28, 2, 3, 15, 6]) {
+        return Some(guess("Linux", None));
+    }
+    None
+}
+
+pub fn guess_from_user_agent(ua: &str) -> Option<Guess> {
+    let has = |s: &str| ua.contains(s);
+    // TVs e consoles primeiro: muitos têm "Android"/"Linux" no User-Agent também
+    let tv = [("Tizen", "Tizen"), ("Web0S", "webOS"), ("webOS", "webOS"), ("CrKey", "Chromecast"), ("AppleTV", "tvOS"),
+              ("tvOS", "tvOS"), ("Roku", "Roku"), ("AFT", "Fire OS"), ("BRAVIA", "Android TV"), ("Android TV", "Android TV"),
+              ("SmartTV", "Smart TV"), ("SMART-TV", "Smart TV"), ("HbbTV", "Smart TV")];
+    if let Some((_, os)) = tv.iter().find(|(k, _)| has(k)) {
+        return Some(guess(os, Some("tv")));
+    }
+    let consoles = [("PlayStation", "PlayStation"), ("Xbox", "Xbox"), ("Nintendo", "Nintendo")];
+    if let Some((_, os)) = consoles.iter().find(|(k, _)| has(k)) {
+        return Some(guess(os, Some("console")));
+    }
+    Some(if has("iPhone") { guess("iOS", Some("celular")) }
+        else if has("iPad") { guess("iPadOS", Some("tablet")) }
+        else if has("Android") { guess("Android", Some(if has("Mobile") { "celular" } else { "tablet" })) }
+        else if has("Windows NT") { guess("Windows", Some("computador")) }
+        else if has("Macintosh") || has("Mac OS X") { guess("macOS", Some("computador")) }
+        else if has("CrOS") { guess("ChromeOS", Some("computador")) }
+        else if has("Linux") { guess("Linux", Some("computador")) }
+        else { return None })
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Evidence {
+    pub signature: String, // assinatura do SYN, lista da opção 55 ou o User-Agent
+    #[serde(flatten)]
+    pub guess: Guess,
+}
+
+const SOURCE_PRIORITY: [&str; 3] = ["user_agent", "dhcp", "syn"];
+const MAX_TLS_DOMAINS: usize = 20; // exemplos de SNI guardados por JA4
+
+// Uma aplicação TLS vista no dispositivo, identificada pelo JA4
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TlsClient {
+    pub ja3: String,
+    pub count: u64,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty", default)]
+    pub domains: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClientFingerprint {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub evidence: BTreeMap<String, Evidence>, // por fonte: syn, dhcp, user_agent
+    #[serde(flatten)]
+    pub best: Guess,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub tls: BTreeMap<String, TlsClient>, // JA4 -> aplicação
+    pub last_seen: f64,
+}
+
+impl ClientFingerprint {
+    fn record(&mut self, source: &str, signature: String, g: Guess) {
+        self.evidence.insert(source.to_string(), Evidence { signature, guess: g });
+        let by_priority = || SOURCE_PRIORITY.iter().filter_map(|s| self.evidence.get(*s));
+        self.best = Guess {
+            os: by_priority().find_map(|e| e.guess.os.clone()),
+            device: by_priority().find_map(|e| e.guess.device.clone()),
+        };
+    }
+}
+
+// Fingerprints por ID de dispositivo (MAC, ou IP enquanto o MAC não é conhecido)
+#[derive(Default)]
+pub struct FingerprintTable {
+    pub clients: BTreeMap<String, ClientFingerprint>,
+    dirty: bool,
+}
+
+impl FingerprintTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &str) -> Self {
+        FingerprintTable { clients: load_json_file(path), dirty: false }
+    }
+
+    pub fn save_if_dirty(&mut self, path: &str) {
+        if self.dirty {
+            save_json_file(path, &self.clients);
+            self.dirty = false;
+        }
+    }
+
+    pub fn feed(&mut self, pkt: &Packet, lan: &LanMatcher, devices: &DeviceTable) {
+        // DHCP vem antes de o cliente ter IP: a chave é o próprio MAC
+        if let Some(msg) = dhcp_payload(pkt).and_then(dhcp::parse) {
+            if msg.from_client {
+                if let Some(g) = guess_from_dhcp(&msg.param_list) {
+                    let signature = msg.param_list.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+                    self.update(msg.mac, "dhcp", signature, g, pkt.ts);
+                }
+            }
+            return;
+        }
+        if pkt.protocol != PROTO_TCP || !lan.is_lan(&pkt.src) { return; }
+        let ip = pkt.src.to_string();
+        if let Some(syn) = &pkt.syn {
+            if let Some(g) = guess_from_syn(syn) {
+                let id = self.device_key(&ip, devices);
+                self.update(id, "syn", syn_signature(syn), g, pkt.ts);
+            }
+        } else if let Some(ua) = http::user_agent(&pkt.payload) {
+            if let Some(g) = guess_from_user_agent(&ua) {
+                let id = self.device_key(&ip, devices);
+                self.update(id, "user_agent", ua, g, pkt.ts);
+            }
+        }
+    }
+
+    // ID do dispositivo; o que foi guardado pelo IP antes do MAC aparecer passa para o MAC
+    fn device_key(&mut self, ip: &str, devices: &DeviceTable) -> String {
+        let id = devices.device_id(ip);
+        if id != ip {
+            if let Some(old) = self.clients.remove(ip) {
+                let entry = self.clients.entry(id.clone()).or_default();
+                for (source, e) in old.evidence {
+                    if !entry.evidence.contains_key(&source) { entry.record(&source, e.signature, e.guess); }
+                }
+                for (ja4, t) in old.tls {
+                    match entry.tls.get_mut(&ja4) {
+                        Some(m) => {
+                            m.count += t.count;
+                            m.first_seen = m.first_seen.min(t.first_seen);
+                            m.domains.extend(t.domains);
+                        }
+                        None => { entry.tls.insert(ja4, t); }
+                    }
+                }
+                self.dirty = true;
+            }
+        }
+        id
+    }
+
+    fn update(&mut self, id: String, source: &str, signature: String, g: Guess, ts: f64) {
+        let fp = self.clients.entry(id).or_default();
+        let same = fp.evidence.get(source).is_some_and(|e| e.signature == signature);
+        if !same {
+            fp.record(source, signature, g);
+            self.dirty = true;
+        }
+        fp.last_seen = ts;
+    }
+
+    // ClientHello visto pelo pipeline. Devolve true quando o JA4 é novo num
+    // dispositivo que já tinha outros: aplicação inesperada ou cliente trocado.
+    pub fn record_tls(&mut self, ip: &str, devices: &DeviceTable, ja3: &str, ja4: &str, domain: &str, ts: f64) -> bool {
+        let id = self.device_key(ip, devices);
+        let fp = self.clients.entry(id).or_default();
+        let unexpected = !fp.tls.is_empty() && !fp.tls.contains_key(ja4);
+        let t = fp.tls.entry(ja4.to_string()).or_insert_with(|| TlsClient { ja3: ja3.to_string(), first_seen: ts, ..Default::default() });
+        t.count += 1;
+        t.last_seen = ts;
+        if t.domains.len() < MAX_TLS_DOMAINS { t.domains.insert(domain.to_string()); }
+        fp.last_seen = ts;
+        self.dirty = true;
+        unexpected
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ClientFingerprint> {
+        self.clients.get(id)
+    }
+}
@@ -0,0 +1,239 @@
+// Tabela de fluxos (5-tupla): início, fim, pacotes e bytes por sentido de cada
+// conexão. TCP fecha por FIN/RST ou inatividade; UDP e o resto, só por inatividade.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::attribution::DomainMap;
+use crate::devices::DeviceTable;
+use crate::lan::{LanMatcher, Segments};
+use crate::packet::{Packet, PROTO_ARP, PROTO_ICMPV6, PROTO_TCP, PROTO_UDP, TCP_FIN, TCP_RST};
+
+pub const TCP_IDLE_TIMEOUT: f64 = 300.0;
+pub const UDP_IDLE_TIMEOUT: f64 = 60.0;
+const SWEEP_INTERVAL: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Flow {
+    pub protocol: String, // "tcp", "udp", "icmpv6" ou o número
+    pub client: String,   // lado da LAN (quem abriu, se os dois são da LAN)
+    pub client_port: u16,
+    pub remote: String,
+    pub remote_port: u16,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<String>, // pelo SNI/Host da conexão ou pelo DNS que o cliente resolveu
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub iface: Option<String>,
+    pub start: f64,
+    pub end: f64,
+    pub packets_out: u64, // cliente -> remoto
+    pub packets_in: u64,
+    pub bytes_out: u64,
+    pub bytes_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub end_reason: Option<String>, // fin, rst, timeout; None enquanto ativo
+}
+
+impl Flow {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    pub fn bytes_total(&self) -> u64 {
+        self.bytes_in + self.bytes_out
+    }
+}
+
+fn protocol_name(p: u8) -> String {
+    match p {
+        PROTO_TCP => "tcp".to_string(),
+        PROTO_UDP => "udp".to_string(),
+        PROTO_ICMPV6 => "icmpv6".to_string(),
+        1 => "icmp".to_string(),
+        n => n.to_string(),
+    }
+}
+
+// (protocolo, cliente, porta do cliente, remoto, porta remota)
+type FlowKey = (u8, IpAddr, u16, IpAddr, u16);
+
+struct ActiveFlow {
+    flow: Flow,
+    fin_out: bool,
+    fin_in: bool,
+}
+
+pub struct FlowTable {
+    lan: LanMatcher,
+    active: HashMap<FlowKey, ActiveFlow>,
+    last_sweep: f64,
+    pub domains: DomainMap, // alimentado com os eventos do DomainPipeline
+    pub ignore_clients: HashSet<String>, // IPs ou MACs, como no ByteCounter
+    pub segments: Segments,
+    pub tcp_idle: f64,
+    pub udp_idle: f64,
+}
+
+impl FlowTable {
+    pub fn new(lan: LanMatcher) -> Self {
+        FlowTable {
+            lan, active: HashMap::new(), last_sweep: 0.0, domains: DomainMap::new(),
+            ignore_clients: HashSet::new(), segments: Segments::default(), tcp_idle: TCP_IDLE_TIMEOUT, udp_idle: UDP_IDLE_TIMEOUT,
+        }
+    }
+
+    // Devolve os fluxos que terminaram (fechados por este pacote ou expirados)
+    pub fn process(&mut self, pkt: &Packet, devices: &DeviceTable) -> Vec<Flow> {
+        let mut done = Vec::new();
+        if pkt.ts - self.last_sweep >= SWEEP_INTERVAL {
+            done = self.expire(pkt.ts);
+            self.last_sweep = pkt.ts;
+        }
+        if pkt.protocol == PROTO_ARP { return done; }
+
+        let forward = (pkt.protocol, pkt.src, pkt.src_port, pkt.dst, pkt.dst_port);
+        let reverse = (pkt.protocol, pkt.dst, pkt.dst_port, pkt.src, pkt.src_port);
+        let (key, outbound) = if self.active.contains_key(&forward) {
+            (forward, true)
+        } else if self.active.contains_key(&reverse) {
+            (reverse, false)
+        } else if self.lan.is_lan(&pkt.src) {
+            (forward, true)
+        } else if self.lan.is_lan(&pkt.dst) {
+            // Captura começou no meio da conexão: a resposta chegou antes
+            (reverse, false)
+        } else {
+            return done;
+        };
+        // Tráfego roteado entre dois segmentos capturados: conta só no do cliente (mesma regra do ByteCounter)
+        if !self.segments.serves(pkt.iface.as_deref(), &key.1) { return done; }
+        let client = key.1.to_string();
+        if self.ignore_clients.contains(&client) || devices.mac_of(&client).is_some_and(|m| self.ignore_clients.contains(m)) { return done; }
+
+        let a = self.active.entry(key).or_insert_with(|| {
+            let client = key.1.to_string();
+            ActiveFlow {
+                flow: Flow {
+                    protocol: protocol_name(key.0),
+                    mac: devices.mac_of(&client).map(str::to_string),
+                    client,
+                    client_port: key.2,
+                    remote: key.3.to_string(),
+                    remote_port: key.4,
+                    iface: pkt.iface.as_deref().map(str::to_string),
+                    start: pkt.ts,
+                    ..Default::default()
+                },
+                fin_out: false,
+                fin_in: false,
+            }
+        });
+        let f = &mut a.flow;
+        if let Some(seen) = pkt.iface.as_deref() { f.iface = Some(seen.to_string()); }
+        f.end = pkt.ts;
+        if outbound {
+            f.packets_out += 1;
+            f.bytes_out += pkt.len;
+        } else {
+            f.packets_in += 1;
+            f.bytes_in += pkt.len;
+        }
+        if f.domain.is_none() {
+            f.domain = self.domains.lookup(&key.1, &key.3).map(str::to_string);
+        }
+
+        if pkt.protocol == PROTO_TCP {
+            if pkt.tcp_flags & TCP_FIN != 0 {
+                if outbound { a.fin_out = true } else { a.fin_in = true }
+            }
+            let reason = if pkt.tcp_flags & TCP_RST != 0 { Some("rst") } else if a.fin_out && a.fin_in { Some("fin") } else { None };
+            if let Some(reason) = reason {
+                let mut flow = self.active.remove(&key).map(|a| a.flow).unwrap_or_default();
+                flow.end_reason = Some(reason.to_string());
+                done.push(flow);
+            }
+        }
+        done
+    }
+
+    fn expire(&mut self, now: f64) -> Vec<Flow> {
+        let (tcp_idle, udp_idle) = (self.tcp_idle, self.udp_idle);
+        let idle = |k: &FlowKey| if k.0 == PROTO_TCP { tcp_idle } else { udp_idle };
+        let expired: Vec<FlowKey> = self.active.iter().filter(|(k, a)| now - a.flow.end > idle(k)).map(|(k, _)| *k).collect();
+        let mut out: Vec<Flow> = expired.into_iter().filter_map(|k| self.active.remove(&k)).map(|a| {
+            let mut f = a.flow;
+            f.end_reason = Some("timeout".to_string());
+            f
+        }).collect();
+        out.sort_by(|a, b| a.start.total_cmp(&b.start));
+        out
+    }
+
+    // Fluxos ainda abertos, para o snapshot que a API mostra como "ativos"
+    pub fn active(&self) -> Vec<Flow> {
+        let mut v: Vec<Flow> = self.active.values().map(|a| a.flow.clone()).collect();
+        v.sort_by(|a, b| a.start.total_cmp(&b.start));
+        v
+    }
+
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+}
+
+// Fluxos terminados vão para um JSONL por dia (data local do fim do fluxo)
+pub fn flows_file(dir: &str, date: &str) -> PathBuf {
+    Path::new(dir).join(format!("flows-{}.jsonl", date))
+}
+
+// Arquivos diários de fluxos (data, caminho, tamanho), mais antigos primeiro
+pub fn flow_days(dir: &str) -> Vec<(String, PathBuf, u64)> {
+    crate::rotation::dated_files(Path::new(dir), "flows-", ".jsonl")
+}
+
+pub fn active_flows_file(dir: &str) -> PathBuf {
+    Path::new(dir).join("flows-active.json")
+}
+
+fn local_date(ts: f64) -> String {
+    let dt: DateTime<Local> = DateTime::from(DateTime::from_timestamp(ts as i64, 0).unwrap_or_default());
+    dt.format("%Y-%m-%d").to_string()
+}
+
+pub fn append_flows(dir: &str, flows: &[Flow]) {
+    if flows.is_empty() { return; }
+    let _ = fs::create_dir_all(dir);
+    let mut by_day: HashMap<String, String> = HashMap::new();
+    for f in flows {
+        if let Ok(line) = serde_json::to_string(f) {
+            let buf = by_day.entry(local_date(f.end)).or_default();
+            buf.push_str(&line);
+            buf.push('\n');
+        }
+    }
+    for (date, lines) in by_day {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(flows_file(dir, &date)) {
+            let _ = file.write_all(lines.as_bytes());
+        }
+    }
+}
+
+// Linhas inválidas (ex: escrita interrompida) são puladas
+pub fn read_flows(path: &Path) -> Vec<Flow> {
+    match File::open(path) {
+        Ok(file) => BufReader::new(file).lines().map_while(Result::ok).filter_map(|l| serde_json::from_str(&l).ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
@@ -0,0 +1,26 @@
+// Extração de cabeçalhos (Host, User-Agent) de requisições HTTP/1.x em claro
+
+const METHODS: [&str; 9] = ["GET ", "POST ", "HEAD ", "PUT ", "DELETE ", "OPTIONS ", "PATCH ", "CONNECT ", "TRACE "];
+
+pub fn request_header(payload: &[u8], wanted: &str) -> Option<String> {
+    if !METHODS.iter().any(|m| payload.starts_with(m.as_bytes())) { return None; }
+    let text = String::from_utf8_lossy(payload);
+    for line in text.split("\r\n").skip(1) {
+        if line.is_empty() { break; }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case(wanted) {
+                let value = value.trim();
+                if !value.is_empty() { return Some(value.to_string()); }
+            }
+        }
+    }
+    None
+}
+
+pub fn request_host(payload: &[u8]) -> Option<String> {
+    request_header(payload, "host")
+}
+
+pub fn user_agent(payload: &[u8]) -> Option<String> {
+    request_header(payload, "user-agent")
+}
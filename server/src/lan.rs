@@ -0,0 +1,152 @@
+// Classificação de endereços: LAN (cliente) x remoto, por redes CIDR
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+// Rede IPv4 ou IPv6 no formato "192.168.1.0/24". IP sem "/n" vale só o host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, len: u8) -> Option<Self> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if len > max { return None; }
+        Some(Cidr { addr, len })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.len as u32).unwrap_or(0);
+                u32::from(*ip) & mask == u32::from(net) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.len as u32).unwrap_or(0);
+                u128::from(*ip) & mask == u128::from(net) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, len) = match s.split_once('/') {
+            Some((a, l)) => (a, Some(l)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| format!("endereço inválido: {}", s))?;
+        let len = match len {
+            Some(l) => l.parse().map_err(|_| format!("prefixo inválido: {}", s))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Cidr::new(addr, len).ok_or_else(|| format!("prefixo fora do intervalo: {}", s))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+// Lista separada por vírgula, espaço ou quebra de linha
+pub fn parse_cidr_list(list: &str) -> Result<Vec<Cidr>, String> {
+    list.split([',', ' ', '\n'])
+        .filter(|p| !p.trim().is_empty())
+        .map(Cidr::from_str)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct LanMatcher {
+    nets: Vec<Cidr>,    // redes da LAN
+    exclude: Vec<Cidr>, // exceções dentro delas (gateway, VLAN de convidados...)
+}
+
+impl LanMatcher {
+    pub fn new(nets: Vec<Cidr>, exclude: Vec<Cidr>) -> Self {
+        LanMatcher { nets, exclude }
+    }
+
+    // LAN_NETS ("192.168.1.0/24,192.168.20.0/24,fd00::/8") e LAN_EXCLUDE ("192.168.1.1,192.168.30.0/24").
+    // No IPv6 o padrão é só ULA e link-local, já que o prefixo global muda com o provedor.
+    pub fn from_env() -> Self {
+        // LAN_REGEX (versões antigas) não é mais lido: sem LAN_NETS, a LAN padrão entraria no lugar sem aviso
+        if let Ok(regex) = std::env::var("LAN_REGEX") {
+            match std::env::var("LAN_NETS") {
+                Ok(_) => println!("[lan] LAN_REGEX ignorado (usando LAN_NETS); pode removê-lo da configuração"),
+                Err(_) => panic!("LAN_REGEX ('{}') não é mais suportado: defina a LAN em LAN_NETS, ex: LAN_NETS=192.168.1.0/24,fd00::/8", regex),
+            }
+        }
+        let nets = std::env::var("LAN_NETS").unwrap_or_else(|_| "192.168.1.0/24,fd00::/8,fe80::/10".to_string());
+        let exclude = std::env::var("LAN_EXCLUDE").unwrap_or_default();
+        LanMatcher::new(
+            parse_cidr_list(&nets).unwrap_or_else(|e| panic!("LAN_NETS: {}", e)),
+            parse_cidr_list(&exclude).unwrap_or_else(|e| panic!("LAN_EXCLUDE: {}", e)),
+        )
+    }
+
+    pub fn is_lan(&self, ip: &IpAddr) -> bool {
+        self.nets.iter().any(|n| n.contains(ip)) && !self.exclude.iter().any(|n| n.contains(ip))
+    }
+
+    pub fn describe(&self) -> String {
+        let nets: Vec<_> = self.nets.iter().map(|n| n.to_string()).collect();
+        let exclude: Vec<_> = self.exclude.iter().map(|n| format!("!{}", n)).collect();
+        [nets, exclude].concat().join(", ")
+    }
+}
+
+// Redes servidas por cada interface capturada. Tráfego roteado entre dois segmentos
+// passa pelas duas bridges: a cópia vista numa interface só conta para um cliente da
+// rede dela (ou de rede que nenhuma interface serve, ex: VPN). Decide por pacote, então
+// um dispositivo que muda de bridge continua sendo contado.
+#[derive(Debug, Clone, Default)]
+pub struct Segments {
+    nets: HashMap<String, Vec<Cidr>>,
+}
+
+impl Segments {
+    // IFACE_NETS ("br-lan=192.168.1.0/24,fd00:1::/64;br-guest=192.168.20.0/24") define as
+    // redes à mão; as demais interfaces usam os endereços configurados nelas (`detect`)
+    pub fn from_env(ifaces: &[String], detect: fn(&str) -> Vec<Cidr>) -> Self {
+        let mut segments = Segments::default();
+        for entry in std::env::var("IFACE_NETS").unwrap_or_default().split(';').filter(|e| !e.trim().is_empty()) {
+            let (iface, nets) = entry.split_once('=').unwrap_or_else(|| panic!("IFACE_NETS: esperado interface=redes em '{}'", entry));
+            segments.insert(iface.trim(), parse_cidr_list(nets).unwrap_or_else(|e| panic!("IFACE_NETS: {}", e)));
+        }
+        for iface in ifaces {
+            if !segments.nets.contains_key(iface) { segments.insert(iface, detect(iface)); }
+        }
+        segments
+    }
+
+    pub fn insert(&mut self, iface: &str, nets: Vec<Cidr>) {
+        if !nets.is_empty() { self.nets.insert(iface.to_string(), nets); }
+    }
+
+    // Pacote visto em `iface` conta para `client`? Não, só se o cliente pertence à rede de outra interface
+    pub fn serves(&self, iface: Option<&str>, client: &IpAddr) -> bool {
+        let Some(iface) = iface else { return true };
+        if self.nets.get(iface).is_some_and(|nets| nets.iter().any(|n| n.contains(client))) { return true; }
+        !self.nets.iter().any(|(other, nets)| other != iface && nets.iter().any(|n| n.contains(client)))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = self.nets.iter()
+            .map(|(iface, nets)| format!("{}={}", iface, nets.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(",")))
+            .collect();
+        parts.sort();
+        parts.join("; ")
+    }
+}
@@ -2,12 +2,33 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::time::{SystemTime, UNIX_EPOCH};
-use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::sync::OnceLock;
 
-// Regex estático compilado uma vez só para performance
-static LOG_REGEX: OnceLock<Regex> = OnceLock::new();
+pub mod attribution;
+pub mod capture;
+pub mod counters;
+pub mod devices;
+pub mod dhcp;
+pub mod dns;
+pub mod eventdb;
+pub mod events;
+pub mod fingerprint;
+pub mod flows;
+pub mod http;
+pub mod lan;
+pub mod logline;
+pub mod names;
+pub mod oui;
+pub mod packet;
+pub mod pipeline;
+pub mod quic;
+pub mod rotation;
+pub mod timeseries;
+pub mod tls;
+
+use logline::LogLine;
+use packet::{Packet, PROTO_TCP, PROTO_UDP};
 
 #[derive(Serialize)]
 pub struct ParsedLogEntry {
@@ -18,9 +39,56 @@ pub struct ParsedLogEntry {
     pub domain: String, // igual ao host na logica atual
     pub remote_ip: String,
     pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ja3: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ja4: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    pub delayed_dns: bool, // DNS sem conexão vista: o remoto é o resolvedor ou, com resposta, o 1º IP resolvido
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<String>, // IPs resolvidos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_rcode: Option<String>, // ex: NXDOMAIN
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>, // ex: "via_dns"
     pub raw: String,
 }
 
+impl ParsedLogEntry {
+    pub fn from_line(l: LogLine, raw: &str) -> Self {
+        ParsedLogEntry {
+            timestamp: l.timestamp,
+            client_ip: l.client_ip,
+            client_name: l.client_name,
+            host: l.domain.clone(),
+            domain: l.domain,
+            remote_ip: l.remote_ip,
+            source: l.source,
+            alpn: l.alpn,
+            tls_version: l.tls_version,
+            ja3: l.ja3,
+            ja4: l.ja4,
+            iface: l.iface,
+            mac: l.mac,
+            delayed_dns: l.delayed_dns,
+            answers: l.answers,
+            dns_ttl: l.dns_ttl,
+            dns_rcode: l.dns_rcode,
+            flags: if l.delayed_dns { vec![events::FLAG_VIA_DNS.to_string()] } else { Vec::new() },
+            raw: raw.to_string(),
+        }
+    }
+}
+
 
 // Retorna epoch atual em segundos (f64)
 pub fn get_current_epoch() -> f64 {
@@ -31,12 +99,10 @@ pub fn get_current_epoch() -> f64 {
 pub fn load_set_from_file(path: &str) -> HashSet<String> {
     let mut set = HashSet::new();
     if let Ok(file) = File::open(path) {
-        for line in BufReader::new(file).lines() {
-            if let Ok(l) = line {
-                let trimmed = l.trim();
-                if !trimmed.is_empty() && !trimmed.starts_with('#') {
-                    set.insert(trimmed.to_string());
-                }
+        for l in BufReader::new(file).lines().map_while(Result::ok) {
+            let trimmed = l.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                set.insert(trimmed.to_string());
             }
         }
     }
@@ -47,50 +113,89 @@ pub fn load_set_from_file(path: &str) -> HashSet<String> {
 pub fn load_map_from_file(path: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
     if let Ok(file) = File::open(path) {
-        for line in BufReader::new(file).lines() {
-            if let Ok(l) = line {
-                let parts: Vec<&str> = l.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    map.insert(parts[0].to_string(), parts[1].to_string());
-                }
+        for l in BufReader::new(file).lines().map_while(Result::ok) {
+            let parts: Vec<&str> = l.split_whitespace().collect();
+            if parts.len() >= 2 {
+                map.insert(normalize_ip(parts[0]), parts[1].to_string());
             }
         }
     }
     map
 }
 
-// Helper para resolver nomes com fallback
-pub fn resolve_client_name(ip: &str, map: &HashMap<String, String>) -> String {
-    match map.get(ip) {
+// Estado persistido em JSON (tabelas aprendidas). Arquivo ausente ou inválido = valor padrão.
+pub fn load_json_file<T: DeserializeOwned + Default>(path: &str) -> T {
+    std::fs::read_to_string(path).ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+// Escreve num temporário e renomeia, para quem lê nunca ver o arquivo pela metade
+pub fn save_json_file<T: Serialize>(path: &str, value: &T) {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(value) {
+        let tmp = format!("{}.tmp", path);
+        if std::fs::write(&tmp, json).is_ok() {
+            let _ = std::fs::rename(&tmp, path);
+        }
+    }
+}
+
+// Forma canônica do IP ("2001:DB8:0::1" -> "2001:db8::1"), igual à usada nos logs e no JSON.
+// O que não é IP (ex: MAC "AA:BB:...") só vai para minúsculas.
+pub fn normalize_ip(ip: &str) -> String {
+    match ip.parse::<std::net::IpAddr>() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => ip.to_ascii_lowercase(),
+    }
+}
+
+// Helper para resolver nomes com fallback: mapa manual (por MAC, depois por IP),
+// nomes aprendidos na rede (DHCP, mDNS...), e por fim o próprio IP
+pub fn resolve_client_name(ip: &str, mac: Option<&str>, map: &HashMap<String, String>, learned: &HashMap<String, String>) -> String {
+    match client_name(ip, mac, map, learned) {
         Some(name) => format!("{} ({})", ip, name),
         None => format!("{} ({})", ip, ip),
     }
 }
-// Regex: [+] DATE | IP (Name) -> HOST (REMOTE) | fonte=SRC
+
+// Nome do dispositivo: manual pelo MAC, manual pelo IP, aprendido na rede
+pub fn client_name<'a>(ip: &str, mac: Option<&str>, map: &'a HashMap<String, String>, learned: &'a HashMap<String, String>) -> Option<&'a str> {
+    mac.and_then(|m| map.get(m)).or_else(|| map.get(ip)).or_else(|| learned.get(ip)).map(String::as_str)
+}
+
+// Payload DNS do pacote (UDP/53 ou TCP/53, este com 2 bytes de tamanho na frente)
+pub fn dns_payload(pkt: &Packet) -> Option<&[u8]> {
+    if pkt.src_port != 53 && pkt.dst_port != 53 { return None; }
+    match pkt.protocol {
+        PROTO_UDP => Some(&pkt.payload),
+        PROTO_TCP => pkt.payload.get(2..),
+        _ => None,
+    }
+}
+
+// Identifica Domínio e Fonte direto do pacote (mesma prioridade de antes: DNS > TLS > HTTP)
+pub fn extract_domain(pkt: &Packet) -> Option<(String, &'static str)> {
+    if let Some(hint) = &pkt.hint {
+        return Some((hint.domain.clone(), hint.source));
+    }
+    if let Some(msg) = dns_payload(pkt) {
+        return dns::query_name(msg).map(|d| (d, "DNS"));
+    }
+    // Sem estado: só pega ClientHellos que cabem num segmento (ver tls::HelloReassembler)
+    match pkt.protocol {
+        PROTO_TCP => tls::client_hello_sni(&pkt.payload).map(|d| (d, "TLS"))
+            .or_else(|| http::request_host(&pkt.payload).map(|d| (d, "HTTP"))),
+        _ => None,
+    }
+}
+
+// Linha do log: texto (logline::LogLine) ou JSONL (events::LogEvent)
 pub fn parse_log_line(line: &str) -> Option<ParsedLogEntry> {
-    let re = LOG_REGEX.get_or_init(|| {
-        Regex::new(r"^\[\+\]\s+([^|]+)\s+\|\s+([0-9a-fA-F\.:]+)\s+\(([^)]+)\)\s+→\s+(.+?)\s+\(([0-9a-fA-F\.:]+)\)(?:\s+\|\s+fonte=([A-Za-z0-9\-_]+))?").unwrap()
-    });
-
-    if let Some(caps) = re.captures(line) {
-        let timestamp = caps[1].trim().to_string();
-        let client_ip = caps[2].trim().to_string();
-        let client_name = caps[3].trim().to_string();
-        let host = caps[4].trim().to_string();
-        let remote_ip = caps[5].trim().to_string();
-        let source = caps.get(6).map_or("", |m| m.as_str()).trim().to_string();
-
-        Some(ParsedLogEntry {
-            timestamp,
-            client_ip,
-            client_name,
-            host: host.clone(),
-            domain: host,
-            remote_ip,
-            source,
-            raw: line.to_string(),
-        })
-    } else {
-        None
+    if line.trim_start().starts_with('{') {
+        return events::parse_event(line).map(|ev| ev.to_entry());
     }
-}
\ No newline at end of file
+    LogLine::parse(line).map(|l| ParsedLogEntry::from_line(l, line))
+}
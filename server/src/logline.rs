@@ -0,0 +1,142 @@
+// Linha do log texto de domínios, nos dois sentidos: quem grava (domains, main)
+// formata por aqui e a API lê pelo mesmo parser, então toda variante que sai
+// também volta. Formato:
+//
+//   [+] DATA | IP (Nome) → DOMÍNIO (REMOTO) | fonte=SRC [| alpn=.. | tls=.. | ja3=.. | ja4=.. | iface=.. | mac=..]
+//   [+] DATA | IP (Nome) → DOMÍNIO (via DNS: RESOLVEDOR) | fonte=DNS [| ttl=.. | rcode=..] [...]
+//   [+] DATA | IP (Nome) → DOMÍNIO (resolvido: IP) | fonte=DNS | answers=IP,IP.. [| ttl=.. | rcode=..] [...]
+//
+// SRC: DNS, TLS, HTTP ou QUIC. As duas últimas são DNS sem conexão vista: sem
+// resposta, o remoto é o resolvedor; com resposta, o primeiro IP resolvido.
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local};
+use regex::Regex;
+
+use crate::pipeline::DomainEvent;
+
+static LOG_REGEX: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LogLine {
+    pub timestamp: String, // "YYYY-MM-DD HH:MM:SS", hora local
+    pub client_ip: String,
+    pub client_name: String, // sem nome conhecido, o próprio IP
+    pub domain: String,
+    pub remote_ip: String,
+    pub source: String, // vazio em linhas antigas, sem "fonte="
+    pub delayed_dns: bool, // DNS que expirou sem conexão TLS/HTTP correspondente
+    pub alpn: Option<String>,
+    pub tls_version: Option<String>,
+    pub ja3: Option<String>,
+    pub ja4: Option<String>,
+    pub iface: Option<String>,
+    pub mac: Option<String>,
+    pub answers: Vec<String>, // IPs resolvidos (DNS com resposta vista)
+    pub dns_ttl: Option<u32>,
+    pub dns_rcode: Option<String>,
+}
+
+pub fn format_timestamp(ts: f64) -> String {
+    let local: DateTime<Local> = DateTime::from(DateTime::from_timestamp(ts as i64, 0).unwrap_or_default());
+    local.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// O que delimita campos não pode aparecer dentro deles
+fn clean_name(s: &str) -> String {
+    s.replace(['(', ')', '|', '\r', '\n'], " ").trim().to_string()
+}
+
+fn clean_token(s: &str) -> String {
+    s.chars().map(|c| if c.is_whitespace() || c == '|' { '_' } else { c }).collect()
+}
+
+impl LogLine {
+    pub fn from_event(ev: &DomainEvent, name: Option<&str>) -> Self {
+        LogLine {
+            timestamp: format_timestamp(ev.ts),
+            client_ip: ev.client.clone(),
+            client_name: name.unwrap_or(&ev.client).to_string(),
+            domain: ev.domain.clone(),
+            remote_ip: ev.remote.clone(),
+            source: ev.source.to_string(),
+            delayed_dns: ev.delayed,
+            alpn: ev.alpn.clone(),
+            tls_version: ev.tls_version.clone(),
+            ja3: ev.ja3.clone(),
+            ja4: ev.ja4.clone(),
+            iface: ev.iface.clone(),
+            mac: ev.mac.clone(),
+            answers: ev.answers.iter().map(|ip| ip.to_string()).collect(),
+            dns_ttl: ev.dns_ttl,
+            dns_rcode: ev.dns_rcode.clone(),
+        }
+    }
+
+    // Linha sem o '\n' final
+    pub fn format(&self) -> String {
+        let name = clean_name(&self.client_name);
+        let name = if name.is_empty() { &self.client_ip } else { &name };
+        let remote = match (self.delayed_dns, self.answers.is_empty()) {
+            (true, true) => format!("via DNS: {}", self.remote_ip),
+            (true, false) => format!("resolvido: {}", self.remote_ip),
+            (false, _) => self.remote_ip.clone(),
+        };
+        let mut line = format!("[+] {} | {} ({}) → {} ({})", self.timestamp, self.client_ip, name, clean_token(&self.domain), remote);
+        if !self.source.is_empty() { line.push_str(&format!(" | fonte={}", self.source)); }
+        for (key, value) in [("alpn", &self.alpn), ("tls", &self.tls_version), ("ja3", &self.ja3), ("ja4", &self.ja4), ("iface", &self.iface), ("mac", &self.mac)] {
+            if let Some(v) = value.as_deref().filter(|v| !v.is_empty()) {
+                line.push_str(&format!(" | {}={}", key, clean_token(v)));
+            }
+        }
+        if !self.answers.is_empty() {
+            let answers: Vec<String> = self.answers.iter().map(|a| clean_token(a)).collect();
+            line.push_str(&format!(" | answers={}", answers.join(",")));
+        }
+        if let Some(ttl) = self.dns_ttl { line.push_str(&format!(" | ttl={}", ttl)); }
+        if let Some(rcode) = self.dns_rcode.as_deref().filter(|r| !r.is_empty()) { line.push_str(&format!(" | rcode={}", clean_token(rcode))); }
+        line
+    }
+
+    pub fn parse(line: &str) -> Option<Self> {
+        let re = LOG_REGEX.get_or_init(|| {
+            Regex::new(r"^\[\+\]\s+([^|]+)\s+\|\s+([0-9a-fA-F\.:]+)\s+\(([^)]+)\)\s+→\s+(.+?)\s+\((via DNS:\s*|resolvido:\s*)?([0-9a-fA-F\.:]*)\)(?:\s+\|\s+fonte=([A-Za-z0-9\-_]+))?((?:\s+\|\s+[a-z0-9_]+=\S+)*)").unwrap()
+        });
+        let caps = re.captures(line)?;
+        let mut parsed = LogLine {
+            timestamp: caps[1].trim().to_string(),
+            client_ip: caps[2].trim().to_string(),
+            client_name: caps[3].trim().to_string(),
+            domain: caps[4].trim().to_string(),
+            delayed_dns: caps.get(5).is_some(),
+            remote_ip: caps[6].trim().to_string(),
+            source: caps.get(7).map_or("", |m| m.as_str()).trim().to_string(),
+            ..Default::default()
+        };
+        // Campos extras "chave=valor" depois da fonte; chave desconhecida é ignorada
+        for kv in caps.get(8).map_or("", |m| m.as_str()).split('|') {
+            let Some((key, v)) = kv.trim().split_once('=') else { continue };
+            let slot = match key {
+                "alpn" => &mut parsed.alpn,
+                "tls" => &mut parsed.tls_version,
+                "ja3" => &mut parsed.ja3,
+                "ja4" => &mut parsed.ja4,
+                "iface" => &mut parsed.iface,
+                "mac" => &mut parsed.mac,
+                "rcode" => &mut parsed.dns_rcode,
+                "answers" => {
+                    parsed.answers = v.split(',').filter(|a| !a.is_empty()).map(str::to_string).collect();
+                    continue;
+                }
+                "ttl" => {
+                    parsed.dns_ttl = v.parse().ok();
+                    continue;
+                }
+                _ => continue,
+            };
+            *slot = Some(v.to_string());
+        }
+        Some(parsed)
+    }
+}
@@ -0,0 +1,222 @@
+// Descoberta passiva de nomes: anúncios mDNS (Apple, Chromecast, impressoras),
+// respostas LLMNR e registros NetBIOS (NBNS) de máquinas Windows
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dns::{self, RecordData, TYPE_PTR};
+use crate::packet::{Packet, PROTO_UDP};
+use crate::{load_json_file, save_json_file};
+
+pub const MDNS_PORT: u16 = 5353;
+pub const LLMNR_PORT: u16 = 5355;
+pub const NBNS_PORT: u16 = 137;
+
+const NBNS_TYPE_NB: u16 = 0x20;
+const NBNS_OP_QUERY: u16 = 0;
+const NBNS_OP_REGISTRATION: u16 = 5;
+const NBNS_OP_REFRESH: u16 = 8;
+const NBNS_OP_MULTIHOMED: u16 = 15;
+
+// O que um pacote anunciou sobre um IP
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    pub ip: IpAddr,
+    pub name: Option<String>,
+    pub services: Vec<String>, // tipos de serviço DNS-SD, ex: "_googlecast._tcp"
+    pub source: &'static str,  // "mDNS", "LLMNR" ou "NBNS"
+}
+
+fn strip_local(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    name.strip_suffix(".local").unwrap_or(name).to_string()
+}
+
+// "_airplay._tcp.local" -> "_airplay._tcp" (o meta-serviço de enumeração fica de fora)
+fn service_type(name: &str) -> Option<String> {
+    let name = strip_local(name);
+    let mut labels = name.rsplitn(3, '.');
+    let proto = labels.next()?;
+    let service = labels.next()?;
+    if (proto != "_tcp" && proto != "_udp") || !service.starts_with('_') { return None; }
+    if service == "_dns-sd" { return None; }
+    Some(format!("{}.{}", service, proto))
+}
+
+// Respostas mDNS: A/AAAA dão o nome do host, PTR de serviço dá o que ele oferece
+fn mdns(pkt: &Packet) -> Vec<Announcement> {
+    let msg = match dns::parse(&pkt.payload) { Some(m) if m.is_response => m, _ => return Vec::new() };
+    let mut by_ip: BTreeMap<IpAddr, Announcement> = BTreeMap::new();
+    let sender = by_ip.entry(pkt.src).or_insert(Announcement { ip: pkt.src, name: None, services: Vec::new(), source: "mDNS" });
+    for r in msg.answers.iter().chain(&msg.additional) {
+        if r.rtype == TYPE_PTR {
+            if let Some(s) = service_type(&r.name).filter(|s| !sender.services.contains(s)) {
+                sender.services.push(s);
+            }
+        }
+    }
+    for r in msg.answers.iter().chain(&msg.additional) {
+        let ip = match r.data {
+            RecordData::A(v4) => IpAddr::V4(v4),
+            RecordData::Aaaa(v6) => IpAddr::V6(v6),
+            _ => continue,
+        };
+        let a = by_ip.entry(ip).or_insert(Announcement { ip, name: None, services: Vec::new(), source: "mDNS" });
+        if a.name.is_none() { a.name = Some(strip_local(&r.name)); }
+    }
+    by_ip.into_values().filter(|a| a.name.is_some() || !a.services.is_empty()).collect()
+}
+
+// Resposta LLMNR: quem responde é o dono do nome
+fn llmnr(pkt: &Packet) -> Vec<Announcement> {
+    let msg = match dns::parse(&pkt.payload) { Some(m) if m.is_response => m, _ => return Vec::new() };
+    msg.answers.iter().filter_map(|r| {
+        let ip = match r.data {
+            RecordData::A(v4) => IpAddr::V4(v4),
+            RecordData::Aaaa(v6) => IpAddr::V6(v6),
+            _ => return None,
+        };
+        Some(Announcement { ip, name: Some(r.name.clone()), services: Vec::new(), source: "LLMNR" })
+    }).collect()
+}
+
+// Nome NetBIOS: 32 letras "A".."P", cada par codifica um byte; o 16º byte é o sufixo
+fn decode_netbios_name(encoded: &str) -> Option<(String, u8)> {
+    let label = encoded.split('.').next()?.as_bytes();
+    if label.len() != 32 { return None; }
+    let mut raw = [0u8; 16];
+    for (i, pair) in label.chunks_exact(2).enumerate() {
+        let hi = pair[0].checked_sub(b'A')?;
+        let lo = pair[1].checked_sub(b'A')?;
+        if hi > 15 || lo > 15 { return None; }
+        raw[i] = (hi << 4) | lo;
+    }
+    let name = String::from_utf8_lossy(&raw[..15]).trim_end().to_string();
+    Some((name, raw[15]))
+}
+
+fn be16(data: &[u8], off: usize) -> Option<u16> {
+    data.get(off..off + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+// Registro/refresh (broadcast na LAN) e respostas positivas de consulta
+fn nbns(pkt: &Packet) -> Vec<Announcement> {
+    let msg = &pkt.payload;
+    let (flags, qdcount) = match (be16(msg, 2), be16(msg, 4)) { (Some(f), Some(q)) => (f, q), _ => return Vec::new() };
+    let is_response = flags & 0x8000 != 0;
+    let opcode = (flags >> 11) & 0x0f;
+    let rcode = flags & 0x000f;
+    let wanted = match opcode {
+        NBNS_OP_QUERY => is_response && rcode == 0,
+        NBNS_OP_REGISTRATION | NBNS_OP_REFRESH | NBNS_OP_MULTIHOMED => !is_response,
+        _ => false,
+    };
+    if !wanted { return Vec::new(); }
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        match dns::read_name(msg, pos) { Some((_, next)) => pos = next + 4, None => return Vec::new() }
+    }
+    let records = [6, 8, 10].iter().filter_map(|&off| be16(msg, off)).map(usize::from).sum::<usize>();
+
+    let mut out = Vec::new();
+    for _ in 0..records {
+        let (name, next) = match dns::read_name(msg, pos) { Some(n) => n, None => break };
+        let (rtype, rdlen) = match (be16(msg, next), be16(msg, next + 8)) { (Some(t), Some(l)) => (t, l as usize), _ => break };
+        let rdata = match msg.get(next + 10..next + 10 + rdlen) { Some(r) => r, None => break };
+        pos = next + 10 + rdlen;
+        if rtype != NBNS_TYPE_NB { continue; }
+        // Só nomes de máquina (sufixo 0x00 estação / 0x20 servidor), não de grupo/domínio
+        let (nb_name, suffix) = match decode_netbios_name(&name) { Some(n) => n, None => continue };
+        if (suffix != 0x00 && suffix != 0x20) || nb_name.is_empty() || nb_name == "*" { continue; }
+        for entry in rdata.chunks_exact(6) {
+            if entry[0] & 0x80 != 0 { continue; } // bit G: nome de grupo
+            let ip = IpAddr::V4(Ipv4Addr::new(entry[2], entry[3], entry[4], entry[5]));
+            if ip.is_unspecified() { continue; }
+            out.push(Announcement { ip, name: Some(nb_name.clone()), services: Vec::new(), source: "NBNS" });
+        }
+    }
+    out
+}
+
+pub fn announcements(pkt: &Packet) -> Vec<Announcement> {
+    if pkt.protocol != PROTO_UDP { return Vec::new(); }
+    match (pkt.src_port, pkt.dst_port) {
+        (MDNS_PORT, _) => mdns(pkt),
+        (LLMNR_PORT, _) => llmnr(pkt),
+        (NBNS_PORT, _) | (_, NBNS_PORT) => nbns(pkt),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct DiscoveredHost {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>, // de onde veio o nome
+    #[serde(skip_serializing_if = "BTreeSet::is_empty", default)]
+    pub services: BTreeSet<String>,
+    pub last_seen: f64,
+}
+
+// Um nome mDNS ("Sala-TV") é mais útil que o NetBIOS em maiúsculas de 15 letras
+fn rank(source: &str) -> u8 {
+    match source {
+        "mDNS" => 3,
+        "LLMNR" => 2,
+        "NBNS" => 1,
+        _ => 0,
+    }
+}
+
+// Tabela de nomes descobertos por IP, persistida em JSON como a de DHCP
+#[derive(Default)]
+pub struct NameTable {
+    pub hosts: BTreeMap<String, DiscoveredHost>,
+}
+
+impl NameTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &str) -> Self {
+        NameTable { hosts: load_json_file(path) }
+    }
+
+    pub fn save(&self, path: &str) {
+        save_json_file(path, &self.hosts);
+    }
+
+    // Devolve true se a tabela mudou (hora de persistir)
+    pub fn feed(&mut self, pkt: &Packet) -> bool {
+        let mut changed = false;
+        for a in announcements(pkt) {
+            changed |= self.learn(&a, pkt.ts);
+        }
+        changed
+    }
+
+    pub fn learn(&mut self, a: &Announcement, ts: f64) -> bool {
+        let host = self.hosts.entry(a.ip.to_string()).or_default();
+        let before = host.clone();
+        if let Some(name) = &a.name {
+            if rank(a.source) >= host.source.as_deref().map_or(0, rank) {
+                host.name = Some(name.clone());
+                host.source = Some(a.source.to_string());
+            }
+        }
+        host.services.extend(a.services.iter().cloned());
+        let changed = *host != before;
+        host.last_seen = ts;
+        changed
+    }
+
+    // IP -> nome, para o fallback de resolve_client_name
+    pub fn names(&self) -> HashMap<String, String> {
+        self.hosts.iter().filter_map(|(ip, h)| Some((ip.clone(), h.name.clone()?))).collect()
+    }
+}
@@ -0,0 +1,92 @@
+// Fabricante pelo prefixo do MAC (OUI), a partir de um arquivo local da IEEE
+// ou do `manuf` do Wireshark. Nada de consulta na rede.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+// Bit "localmente administrado" do primeiro octeto: MAC aleatório (iOS, Android,
+// Windows por rede) ou definido à mão. Não pertence a nenhum fabricante.
+pub fn is_randomized(mac: &str) -> bool {
+    first_octet(mac).is_some_and(|b| b & 0x02 != 0)
+}
+
+fn first_octet(mac: &str) -> Option<u8> {
+    u8::from_str_radix(mac.get(..2)?, 16).ok()
+}
+
+// "3c:22:fb:..." / "3C-22-FB" / "3C22FB" -> 0x3c22fb
+fn prefix24(text: &str) -> Option<u32> {
+    let hex: String = text.chars().filter(|c| *c != ':' && *c != '-' && *c != '.').take(6).collect();
+    if hex.len() != 6 { return None; }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+// Uma linha de qualquer dos formatos aceitos:
+//   oui.txt da IEEE:  "3C-22-FB   (hex)\t\tApple, Inc."
+//   oui.csv da IEEE:  "MA-L,3C22FB,\"Apple, Inc.\",..."
+//   manuf (Wireshark): "3C:22:FB\tApple\tApple, Inc."  (prefixos /28 e /36 são ignorados)
+fn parse_line(line: &str) -> Option<(u32, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') { return None; }
+    if let Some((prefix, vendor)) = line.split_once("(hex)") {
+        return Some((prefix24(prefix.trim())?, vendor.trim().to_string()));
+    }
+    if let Some(rest) = line.strip_prefix("MA-L,") {
+        let (prefix, rest) = rest.split_once(',')?;
+        let vendor = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"')?.0,
+            None => rest.split(',').next()?,
+        };
+        return Some((prefix24(prefix)?, vendor.trim().to_string()));
+    }
+    let mut cols = line.split('\t').map(str::trim).filter(|c| !c.is_empty());
+    let prefix = cols.next()?;
+    if prefix.contains('/') || prefix.len() != 8 { return None; }
+    let short = cols.next()?;
+    let vendor = cols.next().unwrap_or(short);
+    Some((prefix24(prefix)?, vendor.to_string()))
+}
+
+#[derive(Default)]
+pub struct OuiDb {
+    vendors: HashMap<u32, String>,
+}
+
+impl OuiDb {
+    // Arquivo ausente = base vazia (só a detecção de MAC aleatório funciona)
+    pub fn load(path: &str) -> Self {
+        let mut db = OuiDb::default();
+        if let Ok(file) = File::open(path) {
+            for l in BufReader::new(file).lines().map_while(Result::ok) {
+                db.insert_line(&l);
+            }
+        }
+        db
+    }
+
+    pub fn insert_line(&mut self, line: &str) {
+        if let Some((prefix, vendor)) = parse_line(line) {
+            self.vendors.entry(prefix).or_insert(vendor);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+
+    pub fn vendor(&self, mac: &str) -> Option<&str> {
+        if is_randomized(mac) { return None; }
+        self.vendors.get(&prefix24(mac)?).map(String::as_str)
+    }
+
+    // O que o dashboard mostra ao lado do IP: fabricante, "randomized" ou nada
+    pub fn label(&self, mac: &str) -> Option<String> {
+        if is_randomized(mac) { return Some("randomized".to_string()); }
+        self.vendor(mac).map(str::to_string)
+    }
+}
@@ -0,0 +1,246 @@
+// Decodificação mínima de quadros Ethernet -> IPv4/IPv6 -> TCP/UDP
+// (substitui os campos que antes vinham prontos do tshark)
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+pub const PROTO_TCP: u8 = 6;
+pub const PROTO_UDP: u8 = 17;
+pub const PROTO_ICMPV6: u8 = 58;
+// Não é número de protocolo IP: marca pacotes ARP (src/dst = IPs de sender/target,
+// payload = corpo do ARP). Serve só para a tabela de dispositivos.
+pub const PROTO_ARP: u8 = 0xff;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+pub struct Packet {
+    pub ts: f64,
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub protocol: u8,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub len: u64,         // tamanho do payload L4 segundo os cabeçalhos (o mesmo que o tcpdump -q mostra)
+    pub payload: Vec<u8>, // payload da camada 4 (ou do IP, se não for TCP/UDP)
+    pub hint: Option<DomainHint>, // domínio já extraído pela fonte (ex: linhas do tshark)
+    pub iface: Option<Arc<str>>,  // interface de captura (None em replay/stdin)
+    pub syn: Option<SynInfo>,     // só em SYN puro (abertura de conexão), para fingerprint
+    pub tcp_flags: u8,            // flags TCP (0 em outros protocolos)
+    pub seq: u32,                 // número de sequência TCP (0 em outros protocolos)
+}
+
+// Características do SYN que variam por sistema operacional
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynInfo {
+    pub ttl: u8,          // TTL/hop limit como chegou (já descontados os saltos)
+    pub window: u16,
+    pub mss: Option<u16>,
+    pub wscale: Option<u8>,
+    pub options: String,  // ordem das opções, ex: "mss,nop,ws,nop,nop,ts,sok,eol"
+}
+
+pub struct DomainHint {
+    pub domain: String,
+    pub source: &'static str,
+}
+
+impl Packet {
+    pub fn new(ts: f64, src: IpAddr, dst: IpAddr, protocol: u8) -> Self {
+        Packet { ts, src, dst, protocol, src_port: 0, dst_port: 0, len: 0, payload: Vec::new(), hint: None, iface: None, syn: None, tcp_flags: 0, seq: 0 }
+    }
+
+    // Pacote TCP/UDP já decodificado, para o gerador sintético e testes
+    pub fn with_transport(mut self, src_port: u16, dst_port: u16, payload: &[u8]) -> Self {
+        self.src_port = src_port;
+        self.dst_port = dst_port;
+        self.len = payload.len() as u64;
+        self.payload = payload.to_vec();
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u8) -> Self {
+        self.tcp_flags = flags;
+        self
+    }
+
+    pub fn with_seq(mut self, seq: u32) -> Self {
+        self.seq = seq;
+        self
+    }
+
+    pub fn on_iface(mut self, iface: &str) -> Self {
+        self.iface = Some(iface.into());
+        self
+    }
+}
+
+fn be16(data: &[u8], off: usize) -> Option<u16> {
+    data.get(off..off + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+// Decodifica um quadro Ethernet (com ou sem tag VLAN). Retorna None para
+// tudo que não for IP/ARP ou estiver truncado.
+pub fn decode_ethernet(ts: f64, frame: &[u8]) -> Option<Packet> {
+    let mut ethertype = be16(frame, 12)?;
+    let mut off = 14;
+    while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+        ethertype = be16(frame, off + 2)?;
+        off += 4;
+    }
+    decode_ethertype(ts, ethertype, frame.get(off..)?)
+}
+
+// Cabeçalho "Linux cooked" (pcap gravado com -i any)
+pub fn decode_linux_sll(ts: f64, frame: &[u8]) -> Option<Packet> {
+    decode_ethertype(ts, be16(frame, 14)?, frame.get(16..)?)
+}
+
+// Pacote IP sem camada de enlace (LINKTYPE_RAW)
+pub fn decode_raw_ip(ts: f64, data: &[u8]) -> Option<Packet> {
+    match *data.first()? >> 4 {
+        4 => decode_ipv4(ts, data),
+        6 => decode_ipv6(ts, data),
+        _ => None,
+    }
+}
+
+fn decode_ethertype(ts: f64, ethertype: u16, data: &[u8]) -> Option<Packet> {
+    match ethertype {
+        ETHERTYPE_IPV4 => decode_ipv4(ts, data),
+        ETHERTYPE_IPV6 => decode_ipv6(ts, data),
+        ETHERTYPE_ARP => decode_arp(ts, data),
+        _ => None,
+    }
+}
+
+// ARP sobre Ethernet/IPv4: hw=1, proto=0x0800, tamanhos 6 e 4
+pub fn decode_arp(ts: f64, data: &[u8]) -> Option<Packet> {
+    if be16(data, 0)? != 1 || be16(data, 2)? != ETHERTYPE_IPV4 || data.get(4..6)? != [6, 4] { return None; }
+    let body = data.get(..28)?;
+    let src = IpAddr::V4(Ipv4Addr::new(body[14], body[15], body[16], body[17]));
+    let dst = IpAddr::V4(Ipv4Addr::new(body[24], body[25], body[26], body[27]));
+    let mut pkt = Packet::new(ts, src, dst, PROTO_ARP);
+    pkt.payload = body.to_vec();
+    Some(pkt)
+}
+
+pub fn decode_ipv4(ts: f64, data: &[u8]) -> Option<Packet> {
+    if data.len() < 20 || data[0] >> 4 != 4 { return None; }
+    let ihl = ((data[0] & 0x0f) as usize) * 4;
+    let total_len = be16(data, 2)? as usize;
+    if ihl < 20 || total_len < ihl { return None; }
+    // Fragmentos que não são o primeiro não têm cabeçalho L4
+    let frag_offset = be16(data, 6)? & 0x1fff;
+    if frag_offset != 0 { return None; }
+
+    let protocol = data[9];
+    let src = IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15]));
+    let dst = IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19]));
+    // Ethernet pode ter padding no fim; confia no total_len do IP
+    let end = total_len.min(data.len());
+    decode_transport(ts, src, dst, protocol, data[8], total_len - ihl, data.get(ihl..end)?)
+}
+
+// Cabeçalhos de extensão que podem vir entre o IPv6 e a camada 4
+const EXT_HOP_BY_HOP: u8 = 0;
+const EXT_ROUTING: u8 = 43;
+const EXT_FRAGMENT: u8 = 44;
+const EXT_AUTH: u8 = 51;
+const EXT_DEST_OPTS: u8 = 60;
+
+pub fn decode_ipv6(ts: f64, data: &[u8]) -> Option<Packet> {
+    if data.len() < 40 || data[0] >> 4 != 6 { return None; }
+    let payload_len = be16(data, 4)? as usize;
+    let src_octets: [u8; 16] = data[8..24].try_into().ok()?;
+    let dst_octets: [u8; 16] = data[24..40].try_into().ok()?;
+    let src = IpAddr::V6(Ipv6Addr::from(src_octets));
+    let dst = IpAddr::V6(Ipv6Addr::from(dst_octets));
+
+    let mut next = data[6];
+    let mut off = 40;
+    loop {
+        match next {
+            EXT_HOP_BY_HOP | EXT_ROUTING | EXT_DEST_OPTS => {
+                next = *data.get(off)?;
+                off += (*data.get(off + 1)? as usize + 1) * 8;
+            }
+            EXT_AUTH => {
+                next = *data.get(off)?;
+                off += (*data.get(off + 1)? as usize + 2) * 4;
+            }
+            EXT_FRAGMENT => {
+                // Fragmentos que não são o primeiro não têm cabeçalho L4
+                if be16(data, off + 2)? & 0xfff8 != 0 { return None; }
+                next = *data.get(off)?;
+                off += 8;
+            }
+            _ => break,
+        }
+    }
+
+    let end = (40 + payload_len).min(data.len());
+    let wire_len = (40 + payload_len).checked_sub(off)?;
+    decode_transport(ts, src, dst, next, data[7], wire_len, data.get(off..end)?)
+}
+
+// `wire_len` é o tamanho L4 declarado pelo IP; `l4` pode vir truncado pelo snaplen
+fn decode_transport(ts: f64, src: IpAddr, dst: IpAddr, protocol: u8, ttl: u8, wire_len: usize, l4: &[u8]) -> Option<Packet> {
+    let mut syn = None;
+    let mut tcp_flags = 0;
+    let mut seq = 0;
+    let (src_port, dst_port, len, payload) = match protocol {
+        PROTO_TCP => {
+            let data_off = ((*l4.get(12)? >> 4) as usize) * 4;
+            if data_off < 20 || l4.len() < data_off { return None; }
+            tcp_flags = l4[13];
+            seq = u32::from_be_bytes([l4[4], l4[5], l4[6], l4[7]]);
+            if tcp_flags & (TCP_SYN | TCP_ACK) == TCP_SYN {
+                syn = Some(syn_info(ttl, be16(l4, 14)?, &l4[20..data_off]));
+            }
+            (be16(l4, 0)?, be16(l4, 2)?, wire_len.saturating_sub(data_off), &l4[data_off..])
+        }
+        PROTO_UDP => {
+            if l4.len() < 8 { return None; }
+            let udp_len = be16(l4, 4)? as usize;
+            (be16(l4, 0)?, be16(l4, 2)?, udp_len.saturating_sub(8), &l4[8..])
+        }
+        _ => (0, 0, wire_len, l4),
+    };
+    Some(Packet { ts, src, dst, protocol, src_port, dst_port, len: len as u64, payload: payload.to_vec(), hint: None, iface: None, syn, tcp_flags, seq })
+}
+
+pub const TCP_FIN: u8 = 0x01;
+pub const TCP_SYN: u8 = 0x02;
+pub const TCP_RST: u8 = 0x04;
+pub const TCP_ACK: u8 = 0x10;
+
+fn syn_info(ttl: u8, window: u16, opts: &[u8]) -> SynInfo {
+    let mut info = SynInfo { ttl, window, mss: None, wscale: None, options: String::new() };
+    let mut names = Vec::new();
+    let mut pos = 0;
+    while pos < opts.len() {
+        let kind = opts[pos];
+        let len = match kind {
+            0 | 1 => 1,
+            _ => match opts.get(pos + 1) { Some(&l) if l >= 2 => l as usize, _ => break },
+        };
+        let body = opts.get(pos + 2..pos + len).unwrap_or_default();
+        names.push(match kind {
+            0 => "eol".to_string(),
+            1 => "nop".to_string(),
+            2 => { info.mss = be16(body, 0); "mss".to_string() }
+            3 => { info.wscale = body.first().copied(); "ws".to_string() }
+            4 => "sok".to_string(),
+            8 => "ts".to_string(),
+            k => format!("?{}", k),
+        });
+        if kind == 0 { break; }
+        pos += len;
+    }
+    info.options = names.join(",");
+    info
+}
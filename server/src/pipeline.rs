@@ -0,0 +1,209 @@
+// Agrupamento DNS + TLS/HTTP e controle de flood do sniffer de domínios,
+// independente de onde vêm os pacotes (ver capture::PacketSource)
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use crate::devices::DeviceTable;
+use crate::dns::{self, DnsMessage};
+use crate::http;
+use crate::lan::LanMatcher;
+use crate::packet::{Packet, PROTO_UDP};
+use crate::quic::QuicTracker;
+use crate::tls::{ClientHello, HelloReassembler};
+use crate::{dns_payload, extract_domain};
+
+pub const GROUP_WINDOW: f64 = 5.0; // Janela para agrupar DNS + conexão real
+const FLOOD_WINDOW: f64 = 1.0;    // Mesma linha repetida em menos de 1s é descartada
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainEvent {
+    pub ts: f64,
+    pub client: String,
+    pub domain: String,
+    pub remote: String,
+    pub source: &'static str,
+    pub delayed: bool, // DNS que expirou sem conexão TLS/HTTP correspondente
+    pub answers: Vec<IpAddr>, // IPs resolvidos (só para eventos DNS com resposta vista)
+    pub dns_ttl: Option<u32>, // menor TTL da resposta
+    pub dns_rcode: Option<String>, // erro da resposta (ex: NXDOMAIN); None sem erro ou sem resposta
+    pub alpn: Option<String>, // protocolos oferecidos no ClientHello, ex: "h2,http/1.1"
+    pub tls_version: Option<String>,
+    pub ja3: Option<String>, // fingerprints do ClientHello (TLS e QUIC)
+    pub ja4: Option<String>,
+    pub iface: Option<String>, // interface/segmento onde o pacote foi visto
+    pub mac: Option<String>,   // MAC do cliente, se o vínculo IP<->MAC já é conhecido
+}
+
+struct DnsPending {
+    timestamp: f64,
+    remote_ip: String,      // resolvedor
+    queries: Vec<(u16, String)>, // (id, resolvedor) das queries vistas: a resposta tem que bater com uma delas
+    answers: Vec<IpAddr>,   // preenchido quando a resposta passa
+    ttl: Option<u32>,
+    rcode: Option<String>,
+    iface: Option<String>,
+}
+
+pub struct DomainPipeline {
+    lan: LanMatcher,
+    pub ignore_domains: HashSet<String>,
+    pub ignore_clients: HashSet<String>, // IPs ou MACs
+    pub devices: DeviceTable,
+    dns_cache: HashMap<(String, String), DnsPending>,
+    last_log_map: HashMap<String, f64>,
+    next_flood_prune: f64,
+    tls: HelloReassembler,
+    quic: QuicTracker,
+}
+
+impl DomainPipeline {
+    pub fn new(lan: LanMatcher) -> Self {
+        DomainPipeline {
+            lan,
+            ignore_domains: HashSet::new(),
+            ignore_clients: HashSet::new(),
+            devices: DeviceTable::new(),
+            dns_cache: HashMap::new(),
+            last_log_map: HashMap::new(),
+            next_flood_prune: 0.0,
+            tls: HelloReassembler::new(),
+            quic: QuicTracker::new(),
+        }
+    }
+
+    // Processa um pacote e devolve os eventos prontos para o log (pode haver
+    // DNS pendentes expirados junto com o evento do próprio pacote)
+    pub fn process(&mut self, pkt: &Packet) -> Vec<DomainEvent> {
+        let mut out = Vec::new();
+        self.devices.feed(pkt);
+        let (domain, fonte, hello) = match self.identify(pkt) { Some(d) => d, None => return out };
+        if self.ignore_domains.contains(&domain) { return out; }
+
+        let dns_msg = if fonte == "DNS" { dns_payload(pkt).and_then(dns::parse) } else { None };
+        let is_response = dns_msg.as_ref().is_some_and(|m| m.is_response);
+
+        let src = pkt.src.to_string();
+        let dst = pkt.dst.to_string();
+        let src_lan = self.lan.is_lan(&pkt.src);
+        let dst_lan = self.lan.is_lan(&pkt.dst);
+        let (client, remote) = if is_response && dst_lan { (dst, src) } // resposta volta do resolvedor para o cliente
+        else if src_lan && !dst_lan { (src, dst) }
+        else if dst_lan && !src_lan { (dst, src) }
+        else if src_lan && dst_lan { (src, dst) }
+        else { return out; };
+
+        let mac = self.devices.mac_of(&client).map(str::to_string);
+        if self.ignore_clients.contains(&client) || mac.as_ref().is_some_and(|m| self.ignore_clients.contains(m)) { return out; }
+        let ts_pkt = pkt.ts;
+
+        // Limpeza Cache DNS
+        let expired: Vec<_> = self.dns_cache.iter()
+            .filter(|(_, v)| ts_pkt - v.timestamp > GROUP_WINDOW)
+            .map(|(k, _)| k.clone()).collect();
+        out.extend(self.take_dns(expired));
+        // Chaves de flood fora da janela não servem mais (varredura no máximo uma vez por janela)
+        if ts_pkt >= self.next_flood_prune {
+            self.last_log_map.retain(|_, last| ts_pkt - *last <= FLOOD_WINDOW);
+            self.next_flood_prune = ts_pkt + FLOOD_WINDOW;
+        }
+
+        // Deduplicação. A interface fica fora da chave de flood: tráfego roteado entre
+        // dois segmentos capturados aparece nos dois e deve virar uma linha só.
+        let cache_key = (client.clone(), domain.clone());
+        if let Some(msg) = dns_msg.as_ref().filter(|m| m.is_response) {
+            // A resposta não vira linha própria: só completa o DNS pendente com os IPs
+            self.record_answers(cache_key, msg, pkt, remote);
+            return out;
+        }
+        if fonte == "DNS" {
+            // Qualquer resolvedor (gateway, 8.8.8.8, outro roteador): espera a conexão real e a resposta
+            let pending = self.dns_cache.entry(cache_key).or_insert_with(|| DnsPending {
+                timestamp: ts_pkt, remote_ip: remote.clone(), queries: Vec::new(), answers: Vec::new(), ttl: None, rcode: None, iface: iface_of(pkt),
+            });
+            pending.timestamp = ts_pkt;
+            pending.queries.extend(dns_msg.as_ref().map(|m| (m.id, remote.clone())));
+            pending.remote_ip = remote;
+            return out;
+        } else if fonte == "TLS" || fonte == "HTTP" || fonte == "QUIC" {
+            self.dns_cache.remove(&cache_key);
+        }
+
+        let log_key = format!("{}|{}|{}|{}", client, domain, remote, fonte);
+        if let Some(&last_ts) = self.last_log_map.get(&log_key) {
+            if ts_pkt - last_ts <= FLOOD_WINDOW { return out; }
+        }
+        self.last_log_map.insert(log_key, ts_pkt);
+
+        let alpn = hello.as_ref().map(|h| h.alpn.join(",")).filter(|a| !a.is_empty());
+        let tls_version = hello.as_ref().map(|h| h.version_name().to_string());
+        let ja3 = hello.as_ref().map(|h| h.ja3());
+        let ja4 = hello.as_ref().map(|h| h.ja4(fonte == "QUIC"));
+        out.push(DomainEvent { ts: ts_pkt, client, domain, remote, source: fonte, delayed: false, answers: Vec::new(), dns_ttl: None, dns_rcode: None, alpn, tls_version, ja3, ja4, iface: iface_of(pkt), mac });
+        out
+    }
+
+    // Domínio e fonte do pacote. TLS e QUIC passam por remontadores, porque o
+    // ClientHello pode vir em mais de um segmento/pacote.
+    fn identify(&mut self, pkt: &Packet) -> Option<(String, &'static str, Option<ClientHello>)> {
+        if pkt.hint.is_some() || dns_payload(pkt).is_some() {
+            return extract_domain(pkt).map(|(d, f)| (d, f, None));
+        }
+        if pkt.protocol == PROTO_UDP {
+            let hello = self.quic.feed(pkt)?;
+            return Some((hello.sni.clone()?, "QUIC", Some(hello)));
+        }
+        if let Some(hello) = self.tls.feed(pkt) {
+            let sni = hello.sni.clone()?;
+            return Some((sni, "TLS", Some(hello)));
+        }
+        http::request_host(&pkt.payload).map(|d| (d, "HTTP", None))
+    }
+
+    fn record_answers(&mut self, key: (String, String), msg: &DnsMessage, pkt: &Packet, resolver: String) {
+        let answers = msg.resolved_ips();
+        let ttl = msg.min_ttl();
+        let rcode = (msg.rcode != dns::RCODE_NOERROR).then(|| dns::rcode_name(msg.rcode));
+        let query = (msg.id, resolver);
+        match self.dns_cache.get_mut(&key) {
+            // Resposta da query vista (mesmo id e resolvedor). A e AAAA somam; resposta vazia não apaga a outra.
+            Some(pending) if pending.queries.contains(&query) => {
+                for ip in answers {
+                    if !pending.answers.contains(&ip) { pending.answers.push(ip); }
+                }
+                pending.ttl = match (pending.ttl, ttl) { (Some(a), Some(b)) => Some(a.min(b)), (a, b) => a.or(b) };
+                if pending.answers.is_empty() { pending.rcode = rcode; }
+            }
+            Some(_) => {} // resposta a outra query (id ou resolvedor diferente)
+            // Query não vista (ex: captura começou no meio): vale a própria resposta
+            None => {
+                let remote_ip = query.1.clone();
+                self.dns_cache.insert(key, DnsPending { timestamp: pkt.ts, remote_ip, queries: vec![query], answers, ttl, rcode, iface: iface_of(pkt) });
+            }
+        }
+    }
+
+    // Fim da captura (ex: fim do pcap): DNS ainda pendentes saem como "via DNS"
+    pub fn flush(&mut self) -> Vec<DomainEvent> {
+        let pending: Vec<_> = self.dns_cache.keys().cloned().collect();
+        self.take_dns(pending)
+    }
+
+    // Remove as entradas do cache DNS em ordem de timestamp (saída determinística)
+    fn take_dns(&mut self, keys: Vec<(String, String)>) -> Vec<DomainEvent> {
+        let mut entries: Vec<_> = keys.into_iter().filter_map(|k| self.dns_cache.remove(&k).map(|v| (k, v))).collect();
+        entries.sort_by(|a, b| a.1.timestamp.total_cmp(&b.1.timestamp).then_with(|| a.0.cmp(&b.0)));
+        // Com resposta vista, o remoto passa a ser o primeiro destino resolvido em vez do
+        // resolvedor (o log marca a diferença: "resolvido:" x "via DNS:"); os demais vão em `answers`
+        entries.into_iter().map(|((client, domain), v)| DomainEvent {
+            mac: self.devices.mac_of(&client).map(str::to_string),
+            ts: v.timestamp, client, domain,
+            remote: v.answers.first().map(|ip| ip.to_string()).unwrap_or(v.remote_ip),
+            source: "DNS", delayed: true, answers: v.answers, dns_ttl: v.ttl, dns_rcode: v.rcode, alpn: None, tls_version: None, ja3: None, ja4: None, iface: v.iface,
+        }).collect()
+    }
+}
+
+fn iface_of(pkt: &Packet) -> Option<String> {
+    pkt.iface.as_deref().map(str::to_string)
+}
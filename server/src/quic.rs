@@ -0,0 +1,247 @@
+// SNI de conexões QUIC/HTTP3: deriva as chaves do pacote Initial a partir do
+// Destination Connection ID (RFC 9001 §5), remove a proteção de cabeçalho,
+// decifra o payload e remonta os frames CRYPTO até ter o ClientHello
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::Aes128Gcm;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::packet::{Packet, PROTO_UDP};
+use crate::tls::{parse_client_hello, ClientHello};
+
+const VERSION_1: u32 = 0x0000_0001;
+const VERSION_2: u32 = 0x6b33_43cf;
+const VERSION_DRAFT29: u32 = 0xff00_001d;
+
+const SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17,
+    0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f, 0x0a,
+];
+const SALT_V2: [u8; 20] = [
+    0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93,
+    0x81, 0xbe, 0x6e, 0x26, 0x9d, 0xcb, 0xf9, 0xbd, 0x2e, 0xd9,
+];
+const SALT_DRAFT29: [u8; 20] = [
+    0xaf, 0xbf, 0xec, 0x28, 0x99, 0x93, 0xd2, 0x4c, 0x9e, 0x97,
+    0x86, 0xf1, 0x9c, 0x61, 0x11, 0xe0, 0x43, 0x90, 0xa8, 0x99,
+];
+
+const FRAME_PADDING: u64 = 0x00;
+const FRAME_PING: u64 = 0x01;
+const FRAME_ACK: u64 = 0x02;
+const FRAME_ACK_ECN: u64 = 0x03;
+const FRAME_CRYPTO: u64 = 0x06;
+
+const MAX_CRYPTO_SIZE: u64 = 16 * 1024;
+const ASSEMBLY_TIMEOUT: f64 = 10.0;
+
+// Lê um inteiro de tamanho variável do QUIC (RFC 9000 §16)
+pub fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let first = *data.get(*pos)?;
+    let len = 1usize << (first >> 6);
+    let bytes = data.get(*pos..*pos + len)?;
+    let mut v = (first & 0x3f) as u64;
+    for b in &bytes[1..] {
+        v = (v << 8) | *b as u64;
+    }
+    *pos += len;
+    Some(v)
+}
+
+// HKDF-Expand-Label do TLS 1.3 com contexto vazio
+fn expand_label(hk: &Hkdf<Sha256>, label: &str, len: usize) -> Option<Vec<u8>> {
+    let full = format!("tls13 {}", label);
+    let mut info = (len as u16).to_be_bytes().to_vec();
+    info.push(full.len() as u8);
+    info.extend_from_slice(full.as_bytes());
+    info.push(0);
+    let mut out = vec![0u8; len];
+    hk.expand(&info, &mut out).ok()?;
+    Some(out)
+}
+
+pub struct InitialKeys {
+    pub key: Vec<u8>,
+    pub iv: Vec<u8>,
+    pub hp: Vec<u8>,
+}
+
+// Chaves do lado cliente para o Initial
+pub fn client_initial_keys(version: u32, dcid: &[u8]) -> Option<InitialKeys> {
+    let (salt, prefix) = match version {
+        VERSION_1 => (&SALT_V1, "quic"),
+        VERSION_2 => (&SALT_V2, "quicv2"),
+        VERSION_DRAFT29 => (&SALT_DRAFT29, "quic"),
+        _ => return None,
+    };
+    let (initial_secret, _) = Hkdf::<Sha256>::extract(Some(salt), dcid);
+    let initial = Hkdf::<Sha256>::from_prk(&initial_secret).ok()?;
+    let client_secret = expand_label(&initial, "client in", 32)?;
+    let client = Hkdf::<Sha256>::from_prk(&client_secret).ok()?;
+    Some(InitialKeys {
+        key: expand_label(&client, &format!("{} key", prefix), 16)?,
+        iv: expand_label(&client, &format!("{} iv", prefix), 12)?,
+        hp: expand_label(&client, &format!("{} hp", prefix), 16)?,
+    })
+}
+
+fn is_initial(version: u32, first: u8) -> bool {
+    let long_type = (first >> 4) & 0x03;
+    match version {
+        VERSION_2 => long_type == 0b01,
+        _ => long_type == 0b00,
+    }
+}
+
+pub struct InitialPacket {
+    pub version: u32,
+    pub dcid: Vec<u8>,
+    pub payload: Vec<u8>, // frames já decifrados
+}
+
+// Decifra o primeiro pacote do datagrama, se for um Initial de versão conhecida
+pub fn decrypt_initial(datagram: &[u8]) -> Option<InitialPacket> {
+    let first = *datagram.first()?;
+    if first & 0xc0 != 0xc0 { return None; } // precisa ser long header com fixed bit
+    let version = u32::from_be_bytes(datagram.get(1..5)?.try_into().ok()?);
+    if !is_initial(version, first) { return None; }
+
+    let mut pos = 5;
+    let dcid_len = *datagram.get(pos)? as usize;
+    let dcid = datagram.get(pos + 1..pos + 1 + dcid_len)?.to_vec();
+    pos += 1 + dcid_len;
+    let scid_len = *datagram.get(pos)? as usize;
+    pos += 1 + scid_len;
+    let token_len = read_varint(datagram, &mut pos)? as usize;
+    pos += token_len;
+    let length = read_varint(datagram, &mut pos)? as usize;
+    let pn_offset = pos;
+    let packet_end = pn_offset + length;
+    if packet_end > datagram.len() || length < 20 { return None; }
+
+    let keys = client_initial_keys(version, &dcid)?;
+
+    // Proteção de cabeçalho: máscara = AES-ECB(hp, amostra)
+    let sample = datagram.get(pn_offset + 4..pn_offset + 20)?;
+    let hp = Aes128::new_from_slice(&keys.hp).ok()?;
+    let mut mask = aes::Block::clone_from_slice(sample);
+    hp.encrypt_block(&mut mask);
+
+    let mut header = datagram[..pn_offset].to_vec();
+    header[0] ^= mask[0] & 0x0f;
+    let pn_len = (header[0] & 0x03) as usize + 1;
+    let mut pn: u64 = 0;
+    for i in 0..pn_len {
+        let b = datagram[pn_offset + i] ^ mask[1 + i];
+        header.push(b);
+        pn = (pn << 8) | b as u64;
+    }
+
+    let mut nonce = keys.iv.clone();
+    for (i, b) in pn.to_be_bytes().iter().enumerate() {
+        nonce[4 + i] ^= b;
+    }
+    let cipher = Aes128Gcm::new_from_slice(&keys.key).ok()?;
+    let ciphertext = &datagram[pn_offset + pn_len..packet_end];
+    let payload = cipher.decrypt(aes_gcm::Nonce::from_slice(&nonce), Payload { msg: ciphertext, aad: &header }).ok()?;
+
+    Some(InitialPacket { version, dcid, payload })
+}
+
+// Extrai os pedaços (offset, dados) dos frames CRYPTO de um payload decifrado
+pub fn crypto_frames(payload: &[u8]) -> Option<Vec<(u64, Vec<u8>)>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        match read_varint(payload, &mut pos)? {
+            FRAME_PADDING | FRAME_PING => {}
+            FRAME_CRYPTO => {
+                let offset = read_varint(payload, &mut pos)?;
+                let len = read_varint(payload, &mut pos)? as usize;
+                out.push((offset, payload.get(pos..pos + len)?.to_vec()));
+                pos += len;
+            }
+            t @ (FRAME_ACK | FRAME_ACK_ECN) => {
+                read_varint(payload, &mut pos)?; // largest acked
+                read_varint(payload, &mut pos)?; // delay
+                let ranges = read_varint(payload, &mut pos)?;
+                read_varint(payload, &mut pos)?; // first range
+                for _ in 0..ranges * 2 {
+                    read_varint(payload, &mut pos)?;
+                }
+                if t == FRAME_ACK_ECN {
+                    for _ in 0..3 {
+                        read_varint(payload, &mut pos)?;
+                    }
+                }
+            }
+            // Outros frames não aparecem no Initial do cliente antes do ClientHello
+            _ => break,
+        }
+    }
+    Some(out)
+}
+
+type ConnKey = (IpAddr, u16, Vec<u8>);
+
+struct PartialCrypto {
+    first_ts: f64,
+    chunks: BTreeMap<u64, Vec<u8>>,
+}
+
+impl PartialCrypto {
+    // Bytes contíguos a partir do offset 0
+    fn contiguous(&self) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        for (&offset, data) in &self.chunks {
+            let end = offset as usize + data.len();
+            if offset as usize > out.len() { break; }
+            if end > out.len() {
+                out.extend_from_slice(&data[out.len() - offset as usize..]);
+            }
+        }
+        out
+    }
+}
+
+// Junta os frames CRYPTO de um ou mais Initials da mesma conexão
+// (ClientHellos grandes, com key share pós-quântico, ocupam dois pacotes)
+#[derive(Default)]
+pub struct QuicTracker {
+    pending: HashMap<ConnKey, PartialCrypto>,
+}
+
+impl QuicTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, pkt: &Packet) -> Option<ClientHello> {
+        if pkt.protocol != PROTO_UDP || pkt.dst_port != 443 { return None; }
+        let initial = decrypt_initial(&pkt.payload)?;
+        let frames = crypto_frames(&initial.payload)?;
+        if frames.is_empty() { return None; }
+
+        self.pending.retain(|_, p| pkt.ts - p.first_ts <= ASSEMBLY_TIMEOUT);
+        let key = (pkt.src, pkt.src_port, initial.dcid);
+        let partial = self.pending.entry(key.clone()).or_insert_with(|| PartialCrypto { first_ts: pkt.ts, chunks: BTreeMap::new() });
+        for (offset, data) in frames {
+            if offset + data.len() as u64 > MAX_CRYPTO_SIZE { continue; }
+            let slot = partial.chunks.entry(offset).or_default();
+            if data.len() > slot.len() { *slot = data; }
+        }
+
+        let stream = partial.contiguous();
+        if stream.len() < 4 { return None; }
+        let needed = 4 + ((stream[1] as usize) << 16 | (stream[2] as usize) << 8 | stream[3] as usize);
+        if stream.len() < needed { return None; }
+        self.pending.remove(&key);
+        parse_client_hello(&stream[..needed])
+    }
+}
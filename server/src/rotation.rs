@@ -0,0 +1,216 @@
+// Rotação do log de domínios na meia-noite local (relógio dos pacotes), no nome
+// que a API procura: traffic-domains.log -> traffic-domains.log-2025-12-01.
+// Dias antigos podem ser comprimidos (.gz) e a retenção apaga os mais velhos
+// por idade e/ou pelo tamanho total dos dias rotacionados.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+// Padrão: sem gzip e sem retenção (só apaga histórico quem pediu, via LOG_RETENTION_*)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RotationConfig {
+    pub gzip: bool,
+    pub keep_days: Option<i64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl RotationConfig {
+    // Log de domínios: LOG_GZIP=1, LOG_RETENTION_DAYS, LOG_RETENTION_MB (total dos dias antigos)
+    pub fn from_env() -> Self {
+        RotationConfig {
+            gzip: env_var("LOG_GZIP").is_some_and(|v| matches!(v.as_str(), "1" | "true" | "yes")),
+            ..Self::retention_from_env("LOG")
+        }
+    }
+
+    // Só a retenção, com variáveis próprias de cada dado: <prefixo>_RETENTION_DAYS e
+    // <prefixo>_RETENTION_MB (ex: FLOW_ para os fluxos). Ausente ou 0 = sem limite.
+    pub fn retention_from_env(prefix: &str) -> Self {
+        RotationConfig {
+            gzip: false,
+            keep_days: retention_days_from_env(&format!("{}_RETENTION_DAYS", prefix)),
+            max_bytes: env_number(&format!("{}_RETENTION_MB", prefix)).filter(|n| *n > 0).map(|mb| mb * 1_048_576),
+        }
+    }
+}
+
+// Dias a manter; ausente ou 0 = sem limite
+pub fn retention_days_from_env(key: &str) -> Option<i64> {
+    env_number(key).filter(|n| *n > 0).map(|n| n as i64)
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn env_number(key: &str) -> Option<u64> {
+    env_var(key).map(|v| v.parse::<u64>().unwrap_or_else(|_| panic!("{}: número inválido '{}'", key, v)))
+}
+
+// traffic-domains.log -> traffic-domains.log-2025-12-01
+pub fn rotated_path(base: &str, date: &str) -> PathBuf {
+    PathBuf::from(format!("{}-{}", base, date))
+}
+
+fn gz(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.gz", path.display()))
+}
+
+pub fn local_date(ts: f64) -> String {
+    let dt: DateTime<Local> = DateTime::from(DateTime::from_timestamp(ts as i64, 0).unwrap_or_default());
+    dt.format("%Y-%m-%d").to_string()
+}
+
+// Dia rotacionado existe, comprimido ou não
+pub fn log_exists(path: &Path) -> bool {
+    path.exists() || gz(path).exists()
+}
+
+// Conteúdo de um arquivo de log: o .gz (se houver) seguido do texto puro
+pub fn read_log(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    let mut found = false;
+    if let Ok(f) = File::open(gz(path)) {
+        MultiGzDecoder::new(f).read_to_string(&mut content)?;
+        found = true;
+    }
+    match fs::read_to_string(path) {
+        Ok(plain) => content.push_str(&plain),
+        Err(e) if !found => return Err(e),
+        Err(_) => {}
+    }
+    Ok(content)
+}
+
+// Dias rotacionados de `base` com tamanho em disco, mais antigos primeiro
+pub fn rotated_days(base: &str) -> Vec<(String, PathBuf, u64)> {
+    let base = Path::new(base);
+    let (Some(dir), Some(name)) = (base.parent(), base.file_name().and_then(|n| n.to_str())) else { return Vec::new() };
+    dated_files(dir, &format!("{}-", name), "")
+}
+
+// Arquivos `prefixo + AAAA-MM-DD + sufixo` (com ou sem .gz) em `dir`, mais antigos primeiro
+pub fn dated_files(dir: &Path, prefix: &str, suffix: &str) -> Vec<(String, PathBuf, u64)> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let mut days: Vec<(String, PathBuf, u64)> = fs::read_dir(dir).into_iter().flatten().flatten()
+        .filter_map(|e| {
+            let file = e.file_name().into_string().ok()?;
+            let date = file.strip_prefix(prefix)?;
+            let date = date.strip_suffix(".gz").unwrap_or(date);
+            let date = date.strip_suffix(suffix)?;
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some((date.to_string(), e.path(), e.metadata().map(|m| m.len()).unwrap_or(0)))
+        })
+        .collect();
+    days.sort();
+    days
+}
+
+// Apaga os dias mais velhos que `keep_days` e, passando de `max_bytes`, os mais antigos
+// até caber. Devolve os que ficaram.
+pub fn apply_retention(mut days: Vec<(String, PathBuf, u64)>, config: &RotationConfig, today: &str) -> Vec<(String, PathBuf, u64)> {
+    if let (Some(keep), Ok(today)) = (config.keep_days, NaiveDate::parse_from_str(today, "%Y-%m-%d")) {
+        let cutoff = (today - Duration::days(keep)).format("%Y-%m-%d").to_string();
+        days.retain(|(date, path, _)| {
+            let expired = *date < cutoff;
+            if expired { let _ = fs::remove_file(path); }
+            !expired
+        });
+    }
+    if let Some(max) = config.max_bytes {
+        let mut total: u64 = days.iter().map(|d| d.2).sum();
+        while total > max && !days.is_empty() {
+            let (_, path, size) = days.remove(0);
+            let _ = fs::remove_file(path);
+            total -= size;
+        }
+    }
+    days
+}
+
+// Meia-noite local do dia mais antigo que `keep_days` mantém (mesmo corte de
+// apply_retention, para o events.db); None = sem limite por idade
+pub fn retention_start(keep_days: Option<i64>, today: &str) -> Option<f64> {
+    let today = NaiveDate::parse_from_str(today, "%Y-%m-%d").ok()?;
+    let first = (today - Duration::days(keep_days?)).and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&first).earliest().map(|t| t.timestamp() as f64)
+}
+
+pub struct RotatingFile {
+    path: String,
+    day: Option<String>, // dia das linhas no arquivo atual
+    pub config: RotationConfig,
+}
+
+impl RotatingFile {
+    // O dia do arquivo que já existe vem do mtime: parado na virada, roda na primeira linha
+    pub fn new(path: &str, config: RotationConfig) -> Self {
+        let day = fs::metadata(path).and_then(|m| m.modified()).ok()
+            .map(|t| DateTime::<Local>::from(t).format("%Y-%m-%d").to_string());
+        RotatingFile { path: path.to_string(), day, config }
+    }
+
+    pub fn append(&mut self, ts: f64, line: &str) {
+        self.roll(ts);
+        if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(&self.path) {
+            let _ = f.write_all(format!("{}\n", line).as_bytes());
+        }
+    }
+
+    // Roda se o dia mudou. Chamado também pelo relógio do loop, para a virada não esperar
+    // a primeira linha do dia seguinte.
+    pub fn roll(&mut self, ts: f64) {
+        let today = local_date(ts);
+        match &self.day {
+            // Só para frente: evento atrasado (DNS "via DNS") de antes da meia-noite não desfaz a virada
+            Some(day) if *day < today => {
+                let day = day.clone();
+                self.rotate(&day);
+                self.maintain(&today);
+                self.day = Some(today);
+            }
+            Some(_) => {}
+            None => self.day = Some(today),
+        }
+    }
+
+    fn rotate(&self, day: &str) {
+        let target = rotated_path(&self.path, day);
+        if self.config.gzip {
+            if compress_into(Path::new(&self.path), &gz(&target)).is_ok() { let _ = fs::remove_file(&self.path); }
+        } else if !target.exists() {
+            let _ = fs::rename(&self.path, &target);
+        } else if let Ok(content) = fs::read(&self.path) {
+            // Já existe (ex: relógio voltou): junta em vez de sobrescrever
+            if let Ok(mut f) = OpenOptions::new().append(true).open(&target) {
+                if f.write_all(&content).is_ok() { let _ = fs::remove_file(&self.path); }
+            }
+        }
+    }
+
+    // Comprime dias antigos ainda em texto (com LOG_GZIP) e aplica a retenção
+    pub fn maintain(&self, today: &str) {
+        if self.config.gzip {
+            for (_, path, _) in rotated_days(&self.path) {
+                if path.extension().is_some_and(|e| e == "gz") { continue; }
+                if compress_into(&path, &gz(&path)).is_ok() { let _ = fs::remove_file(&path); }
+            }
+        }
+        apply_retention(rotated_days(&self.path), &self.config, today);
+    }
+}
+
+// Acrescenta `src` como um membro gzip no fim de `dst` (gzip aceita membros concatenados)
+fn compress_into(src: &Path, dst: &Path) -> io::Result<()> {
+    let content = fs::read(src)?;
+    let file = OpenOptions::new().create(true).append(true).open(dst)?;
+    let mut enc = GzEncoder::new(file, Compression::default());
+    enc.write_all(&content)?;
+    enc.finish()?.sync_all()
+}
@@ -0,0 +1,247 @@
+// Histórico de banda por dispositivo: amostras de 1 minuto em JSONL por dia,
+// reduzidas para 1 hora e 1 dia quando o dia fecha. Cada resolução tem sua
+// retenção; a consulta usa a mais fina que ainda existe para cada dia.
+//
+//   1m-2025-12-01.jsonl   um ponto por (minuto, dispositivo)
+//   1h-2025-12-01.jsonl   um ponto por (hora, dispositivo), gerado do 1m
+//   1d-2025-12.jsonl      um ponto por (dia, dispositivo), um arquivo por mês
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+pub const MINUTE: i64 = 60;
+pub const HOUR: i64 = 3600;
+pub const DAY: i64 = 86400;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub packets_in: u64,
+    pub packets_out: u64,
+}
+
+impl Sample {
+    pub fn add(&mut self, size: u64, outbound: bool) {
+        if outbound {
+            self.bytes_out += size;
+            self.packets_out += 1;
+        } else {
+            self.bytes_in += size;
+            self.packets_in += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Sample) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.packets_in += other.packets_in;
+        self.packets_out += other.packets_out;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub ts: i64, // início do intervalo (epoch)
+    pub client: String, // ID do dispositivo
+    #[serde(flatten)]
+    pub sample: Sample,
+}
+
+fn local(ts: i64) -> DateTime<Local> {
+    DateTime::from(DateTime::from_timestamp(ts, 0).unwrap_or_default())
+}
+
+pub fn local_date(ts: i64) -> String {
+    local(ts).format("%Y-%m-%d").to_string()
+}
+
+// Início do intervalo de `step` segundos que contém `ts`, alinhado na hora
+// local (um passo de 1d começa à meia-noite daqui, não de UTC)
+pub fn bucket_start(ts: i64, step: i64) -> i64 {
+    let offset = local(ts).offset().local_minus_utc() as i64;
+    ts - (ts + offset).rem_euclid(step)
+}
+
+// Dias mantidos de cada resolução
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub minutes: i64,
+    pub hours: i64,
+    pub days: i64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention { minutes: 7, hours: 90, days: 1825 }
+    }
+}
+
+impl Retention {
+    // HISTORY_KEEP_1M / HISTORY_KEEP_1H / HISTORY_KEEP_1D, em dias
+    pub fn from_env() -> Self {
+        let days = |key: &str, default: i64| match std::env::var(key) {
+            Ok(v) if !v.trim().is_empty() => v.trim().parse().unwrap_or_else(|_| panic!("{}: número de dias inválido '{}'", key, v)),
+            _ => default,
+        };
+        let d = Retention::default();
+        Retention { minutes: days("HISTORY_KEEP_1M", d.minutes), hours: days("HISTORY_KEEP_1H", d.hours), days: days("HISTORY_KEEP_1D", d.days) }
+    }
+}
+
+pub struct TimeSeriesStore {
+    dir: PathBuf,
+    pub retention: Retention,
+}
+
+impl TimeSeriesStore {
+    pub fn new(dir: &str) -> Self {
+        TimeSeriesStore { dir: PathBuf::from(dir), retention: Retention::default() }
+    }
+
+    fn file(&self, resolution: &str, part: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.jsonl", resolution, part))
+    }
+
+    // Um minuto fechado; repetir o minuto (ex: restart no meio dele) só soma
+    pub fn append_minute(&self, ts: i64, samples: &HashMap<String, Sample>) {
+        if samples.is_empty() { return; }
+        let _ = fs::create_dir_all(&self.dir);
+        let mut clients: Vec<_> = samples.iter().collect();
+        clients.sort_by(|a, b| a.0.cmp(b.0));
+        let mut lines = String::new();
+        for (client, sample) in clients {
+            if let Ok(line) = serde_json::to_string(&Point { ts, client: client.clone(), sample: *sample }) {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(self.file("1m", &local_date(ts))) {
+            let _ = file.write_all(lines.as_bytes());
+        }
+    }
+
+    // Gera o 1h do dia e o ponto do dia no 1d do mês a partir do 1m. Refazer é
+    // seguro: o 1h é regravado e o dia substituído no arquivo do mês.
+    pub fn compact_day(&self, date: &str) {
+        let minutes = read_points(&self.file("1m", date));
+        if minutes.is_empty() { return; }
+        write_points(&self.file("1h", date), &rollup(&minutes, HOUR));
+        let month = &date[..7.min(date.len())];
+        let path = self.file("1d", month);
+        let mut days: Vec<Point> = read_points(&path).into_iter().filter(|p| local_date(p.ts) != date).collect();
+        days.extend(rollup(&minutes, DAY));
+        days.sort_by(|a, b| a.ts.cmp(&b.ts).then_with(|| a.client.cmp(&b.client)));
+        write_points(&path, &days);
+    }
+
+    // Dias anteriores a `today` com 1m mas sem 1h (ex: o processo estava parado na virada)
+    pub fn compact_pending(&self, today: &str) {
+        for date in self.dates("1m") {
+            if date.as_str() < today && !self.file("1h", &date).exists() {
+                self.compact_day(&date);
+            }
+        }
+    }
+
+    // Apaga o que passou da retenção. O 1m só sai depois de reduzido.
+    pub fn prune(&self, now: i64) {
+        let today = local(now).date_naive();
+        let cutoff = |keep: i64| (today - Duration::days(keep)).format("%Y-%m-%d").to_string();
+        let (min_cut, hour_cut, day_cut) = (cutoff(self.retention.minutes), cutoff(self.retention.hours), cutoff(self.retention.days));
+        for date in self.dates("1m") {
+            if date < min_cut && self.file("1h", &date).exists() {
+                let _ = fs::remove_file(self.file("1m", &date));
+            }
+        }
+        for date in self.dates("1h") {
+            if date < hour_cut { let _ = fs::remove_file(self.file("1h", &date)); }
+        }
+        // Mês inteiro fora da retenção
+        for month in self.dates("1d") {
+            if month.as_str() < &day_cut[..7] { let _ = fs::remove_file(self.file("1d", &month)); }
+        }
+    }
+
+    // Partições de uma resolução ("2025-12-01" ou "2025-12"), em ordem
+    fn dates(&self, resolution: &str) -> Vec<String> {
+        let prefix = format!("{}-", resolution);
+        let mut out: Vec<String> = fs::read_dir(&self.dir).into_iter().flatten().flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter_map(|name| name.strip_prefix(&prefix)?.strip_suffix(".jsonl").map(str::to_string))
+            .collect();
+        out.sort();
+        out
+    }
+
+    // Pontos de [from, to) somados em intervalos de `step` segundos (múltiplo de 60),
+    // por dispositivo. Para cada dia usa a resolução mais grossa que cabe no passo;
+    // se ela não existe (dia de hoje ainda não reduzido, 1m já apagado), a que houver.
+    pub fn query(&self, from: i64, to: i64, step: i64, wanted: impl Fn(&str) -> bool) -> BTreeMap<String, BTreeMap<i64, Sample>> {
+        let mut out: BTreeMap<String, BTreeMap<i64, Sample>> = BTreeMap::new();
+        if to <= from || step < MINUTE { return out; }
+        let mut preferred: Vec<&str> = Vec::new();
+        if step % DAY == 0 { preferred.push("1d"); }
+        if step % HOUR == 0 { preferred.push("1h"); }
+        preferred.extend(["1m", "1h", "1d"]);
+
+        let mut months: HashMap<String, Vec<Point>> = HashMap::new();
+        let last = local(to - 1).date_naive();
+        let mut day: NaiveDate = local(from).date_naive();
+        while day <= last {
+            let date = day.format("%Y-%m-%d").to_string();
+            for resolution in &preferred {
+                let points = if *resolution == "1d" {
+                    let month = months.entry(date[..7].to_string()).or_insert_with(|| read_points(&self.file("1d", &date[..7])));
+                    month.iter().filter(|p| local_date(p.ts) == date).cloned().collect()
+                } else {
+                    read_points(&self.file(resolution, &date))
+                };
+                if points.is_empty() { continue; }
+                for p in points.iter().filter(|p| p.ts >= from && p.ts < to && wanted(&p.client)) {
+                    out.entry(p.client.clone()).or_default().entry(bucket_start(p.ts, step)).or_default().merge(&p.sample);
+                }
+                break;
+            }
+            day += Duration::days(1);
+        }
+        out
+    }
+}
+
+// Soma pontos em intervalos maiores
+pub fn rollup(points: &[Point], step: i64) -> Vec<Point> {
+    let mut acc: BTreeMap<(i64, String), Sample> = BTreeMap::new();
+    for p in points {
+        acc.entry((bucket_start(p.ts, step), p.client.clone())).or_default().merge(&p.sample);
+    }
+    acc.into_iter().map(|((ts, client), sample)| Point { ts, client, sample }).collect()
+}
+
+// Linhas inválidas (ex: escrita interrompida) são puladas
+fn read_points(path: &Path) -> Vec<Point> {
+    match File::open(path) {
+        Ok(file) => BufReader::new(file).lines().map_while(Result::ok).filter_map(|l| serde_json::from_str(&l).ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Temporário + rename: arquivo reduzido nunca fica pela metade
+fn write_points(path: &Path, points: &[Point]) {
+    let mut lines = String::new();
+    for p in points {
+        if let Ok(line) = serde_json::to_string(p) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    if let Ok(mut f) = File::create(&tmp) {
+        if f.write_all(lines.as_bytes()).is_ok() { let _ = fs::rename(&tmp, path); }
+    }
+}
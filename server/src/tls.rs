@@ -0,0 +1,308 @@
+// Parser do ClientHello TLS: SNI, ALPN e versão, com remontagem de
+// registros que chegam quebrados em vários segmentos TCP. Também calcula os
+// fingerprints JA3/JA4, que identificam a aplicação pelo formato do hello.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::packet::{Packet, PROTO_TCP, TCP_ACK, TCP_SYN};
+
+const CONTENT_HANDSHAKE: u8 = 0x16;
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+const EXT_SERVER_NAME: u16 = 0x0000;
+const EXT_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXT_EC_POINT_FORMATS: u16 = 0x000b;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXT_ALPN: u16 = 0x0010;
+const EXT_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+const MAX_HELLO_SIZE: usize = 16 * 1024; // ClientHello maior que isso é descartado
+const ASSEMBLY_TIMEOUT: f64 = 10.0;      // segmentos órfãos são esquecidos depois disso
+const EXPIRY_INTERVAL: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ClientHello {
+    pub sni: Option<String>,
+    pub alpn: Vec<String>,
+    pub legacy_version: u16,
+    pub supported_versions: Vec<u16>,
+    // Campos dos fingerprints, na ordem em que vieram (GREASE incluído)
+    pub cipher_suites: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub groups: Vec<u16>,
+    pub ec_point_formats: Vec<u8>,
+    pub signature_algorithms: Vec<u16>,
+}
+
+impl ClientHello {
+    // Maior versão oferecida (supported_versions do TLS 1.3, senão a legacy_version)
+    pub fn max_version(&self) -> u16 {
+        self.supported_versions.iter().copied().filter(|v| !is_grease(*v)).max().unwrap_or(self.legacy_version)
+    }
+
+    pub fn version_name(&self) -> &'static str {
+        version_name(self.max_version())
+    }
+
+    // "versão,cifras,extensões,grupos,formatos" em decimal, sem GREASE
+    pub fn ja3_string(&self) -> String {
+        let join = |v: &[u16]| v.iter().filter(|x| !is_grease(**x)).map(|x| x.to_string()).collect::<Vec<_>>().join("-");
+        let formats = self.ec_point_formats.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("-");
+        format!("{},{},{},{},{}", self.legacy_version, join(&self.cipher_suites), join(&self.extensions), join(&self.groups), formats)
+    }
+
+    pub fn ja3(&self) -> String {
+        hex(&Md5::digest(self.ja3_string()))
+    }
+
+    // JA4 (FoxIO): "t13d1516h2_<cifras>_<extensões+assinaturas>"; `quic` troca o prefixo por "q"
+    pub fn ja4(&self, quic: bool) -> String {
+        let ciphers: Vec<u16> = self.cipher_suites.iter().copied().filter(|c| !is_grease(*c)).collect();
+        let exts: Vec<u16> = self.extensions.iter().copied().filter(|e| !is_grease(*e)).collect();
+        let version = match self.max_version() {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let alpn = match self.alpn.first().map(|a| a.as_bytes()) {
+            Some([first, .., last]) if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() => format!("{}{}", *first as char, *last as char),
+            Some([only]) if only.is_ascii_alphanumeric() => format!("{}{}", *only as char, *only as char),
+            Some(a) if !a.is_empty() => { let h = hex(a); format!("{}{}", &h[..1], &h[h.len() - 1..]) }
+            _ => "00".to_string(),
+        };
+        let a = format!("{}{}{}{:02}{:02}{}", if quic { 'q' } else { 't' }, version, if self.sni.is_some() { 'd' } else { 'i' },
+                        ciphers.len().min(99), exts.len().min(99), alpn);
+
+        let mut sorted_ciphers = ciphers;
+        sorted_ciphers.sort_unstable();
+        // SNI e ALPN ficam fora do hash (já aparecem na primeira parte)
+        let mut sorted_exts: Vec<u16> = exts.into_iter().filter(|e| *e != EXT_SERVER_NAME && *e != EXT_ALPN).collect();
+        sorted_exts.sort_unstable();
+        let mut c = hex_list(&sorted_exts);
+        if !self.signature_algorithms.is_empty() {
+            c = format!("{}_{}", c, hex_list(&self.signature_algorithms));
+        }
+        format!("{}_{}_{}", a, truncated_sha256(&hex_list(&sorted_ciphers)), truncated_sha256(&c))
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_list(v: &[u16]) -> String {
+    v.iter().map(|x| format!("{:04x}", x)).collect::<Vec<_>>().join(",")
+}
+
+fn truncated_sha256(s: &str) -> String {
+    if s.is_empty() { return "000000000000".to_string(); }
+    hex(&Sha256::digest(s))[..12].to_string()
+}
+
+fn u16_list(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect()
+}
+
+// Valores GREASE (RFC 8701) aparecem em listas só para testar tolerância
+pub fn is_grease(v: u16) -> bool {
+    v & 0x0f0f == 0x0a0a && (v >> 8) == (v & 0xff)
+}
+
+pub fn version_name(v: u16) -> &'static str {
+    match v {
+        0x0304 => "1.3",
+        0x0303 => "1.2",
+        0x0302 => "1.1",
+        0x0301 => "1.0",
+        0x0300 => "SSL3",
+        _ => "?",
+    }
+}
+
+fn be16(data: &[u8], off: usize) -> Option<usize> {
+    data.get(off..off + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+}
+
+// Recebe a mensagem de handshake completa (tipo + tamanho + corpo)
+pub fn parse_client_hello(hs: &[u8]) -> Option<ClientHello> {
+    if *hs.first()? != HANDSHAKE_CLIENT_HELLO { return None; }
+    let mut hello = ClientHello { legacy_version: be16(hs, 4)? as u16, ..Default::default() };
+
+    // tipo(1) + tamanho(3) + versão(2) + random(32)
+    let mut pos = 4 + 2 + 32;
+    let sid_len = *hs.get(pos)? as usize;
+    pos += 1 + sid_len;
+    let cs_len = be16(hs, pos)?;
+    hello.cipher_suites = u16_list(hs.get(pos + 2..pos + 2 + cs_len)?);
+    pos += 2 + cs_len;
+    let comp_len = *hs.get(pos)? as usize;
+    pos += 1 + comp_len;
+    // ClientHello sem extensões (muito antigo)
+    let ext_total = match be16(hs, pos) { Some(n) => n, None => return Some(hello) };
+    pos += 2;
+    let ext_end = (pos + ext_total).min(hs.len());
+
+    while pos + 4 <= ext_end {
+        let ext_type = be16(hs, pos)? as u16;
+        let ext_len = be16(hs, pos + 2)?;
+        let body = hs.get(pos + 4..pos + 4 + ext_len)?;
+        hello.extensions.push(ext_type);
+        match ext_type {
+            EXT_SERVER_NAME => {
+                // lista(2) + tipo(1) + tamanho(2) + nome
+                let name_len = be16(body, 3)?;
+                let name = body.get(5..5 + name_len)?;
+                hello.sni = Some(String::from_utf8_lossy(name).into_owned());
+            }
+            EXT_ALPN => {
+                let mut i = 2;
+                while i < body.len() {
+                    let l = body[i] as usize;
+                    hello.alpn.push(String::from_utf8_lossy(body.get(i + 1..i + 1 + l)?).into_owned());
+                    i += 1 + l;
+                }
+            }
+            EXT_SUPPORTED_VERSIONS => {
+                let list_len = *body.first()? as usize;
+                hello.supported_versions = u16_list(body.get(1..1 + list_len)?);
+            }
+            EXT_SUPPORTED_GROUPS => hello.groups = u16_list(body.get(2..)?),
+            EXT_EC_POINT_FORMATS => hello.ec_point_formats = body.get(1..)?.to_vec(),
+            EXT_SIGNATURE_ALGORITHMS => hello.signature_algorithms = u16_list(body.get(2..)?),
+            _ => {}
+        }
+        pos += 4 + ext_len;
+    }
+    Some(hello)
+}
+
+enum Assembly {
+    Complete(Vec<u8>),
+    Partial,
+    NotTls,
+}
+
+// Junta os fragmentos de handshake dos registros TLS presentes em `stream`
+fn assemble_handshake(stream: &[u8]) -> Assembly {
+    let mut hs = Vec::new();
+    let mut pos = 0;
+    while pos + 5 <= stream.len() {
+        if stream[pos] != CONTENT_HANDSHAKE { return Assembly::NotTls; }
+        let record_len = u16::from_be_bytes([stream[pos + 3], stream[pos + 4]]) as usize;
+        let end = (pos + 5 + record_len).min(stream.len());
+        hs.extend_from_slice(&stream[pos + 5..end]);
+        pos += 5 + record_len;
+
+        if hs.len() >= 4 {
+            if hs[0] != HANDSHAKE_CLIENT_HELLO { return Assembly::NotTls; }
+            let needed = 4 + ((hs[1] as usize) << 16 | (hs[2] as usize) << 8 | hs[3] as usize);
+            if hs.len() >= needed {
+                hs.truncate(needed);
+                return Assembly::Complete(hs);
+            }
+        }
+    }
+    Assembly::Partial
+}
+
+// Só o primeiro segmento (SNI cabendo num pacote), sem estado
+pub fn client_hello_sni(payload: &[u8]) -> Option<String> {
+    match assemble_handshake(payload) {
+        Assembly::Complete(hs) => parse_client_hello(&hs)?.sni,
+        _ => None,
+    }
+}
+
+type FlowKey = (IpAddr, u16, IpAddr, u16);
+
+struct PartialHello {
+    first_ts: f64,
+    base: u32,                       // seq TCP do primeiro byte do ClientHello
+    chunks: BTreeMap<usize, Vec<u8>>, // offset a partir de `base` -> dados
+}
+
+impl PartialHello {
+    // Bytes contíguos a partir do offset 0 (retransmissão sobreposta não duplica)
+    fn contiguous(&self) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        for (&offset, data) in &self.chunks {
+            if offset > out.len() { break; }
+            if offset + data.len() > out.len() {
+                out.extend_from_slice(&data[out.len() - offset..]);
+            }
+        }
+        out
+    }
+}
+
+// Remonta ClientHellos que ocupam mais de um segmento TCP
+// (comum com key shares pós-quânticos, que passam de 1 MSS), pela posição de
+// cada segmento no fluxo: fora de ordem e retransmissões não embaralham o hello
+#[derive(Default)]
+pub struct HelloReassembler {
+    pending: HashMap<FlowKey, PartialHello>,
+    syns: HashMap<FlowKey, (f64, u32)>, // seq inicial do cliente, até o primeiro segmento com dados
+    next_expiry: f64,
+}
+
+impl HelloReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, pkt: &Packet) -> Option<ClientHello> {
+        if pkt.protocol != PROTO_TCP { return None; }
+        self.expire(pkt.ts);
+        let key = (pkt.src, pkt.src_port, pkt.dst, pkt.dst_port);
+        if pkt.tcp_flags & (TCP_SYN | TCP_ACK) == TCP_SYN {
+            self.syns.insert(key, (pkt.ts, pkt.seq.wrapping_add(1)));
+            return None;
+        }
+        if pkt.payload.is_empty() { return None; }
+
+        if !self.pending.contains_key(&key) {
+            // Com o SYN visto, o começo do fluxo é conhecido e um segmento adiantado espera
+            // pelo primeiro; sem ele (captura começou depois), só o que abre um handshake inicia
+            let base = match self.syns.remove(&key) {
+                Some((_, base)) if pkt.seq != base || pkt.payload[0] == CONTENT_HANDSHAKE => base,
+                None if pkt.payload[0] == CONTENT_HANDSHAKE => pkt.seq,
+                _ => return None,
+            };
+            self.pending.insert(key, PartialHello { first_ts: pkt.ts, base, chunks: BTreeMap::new() });
+        }
+        let partial = self.pending.get_mut(&key)?;
+        let offset = pkt.seq.wrapping_sub(partial.base) as usize;
+        // Antes do início (wrap) ou além do tamanho máximo: não é parte do hello
+        if offset + pkt.payload.len() > MAX_HELLO_SIZE { return None; }
+        let slot = partial.chunks.entry(offset).or_default();
+        if pkt.payload.len() > slot.len() { *slot = pkt.payload.clone(); }
+
+        let stream = partial.contiguous();
+        if stream.is_empty() { return None; }
+        match assemble_handshake(&stream) {
+            Assembly::Complete(hs) => {
+                self.pending.remove(&key);
+                parse_client_hello(&hs)
+            }
+            Assembly::Partial if stream.len() < MAX_HELLO_SIZE => None,
+            _ => {
+                self.pending.remove(&key);
+                None
+            }
+        }
+    }
+
+    // Esquece os órfãos no máximo uma vez por EXPIRY_INTERVAL, e só se houver algo pendente
+    fn expire(&mut self, now: f64) {
+        if now < self.next_expiry || (self.pending.is_empty() && self.syns.is_empty()) { return; }
+        self.pending.retain(|_, p| now - p.first_ts <= ASSEMBLY_TIMEOUT);
+        self.syns.retain(|_, s| now - s.0 <= ASSEMBLY_TIMEOUT);
+        self.next_expiry = now + EXPIRY_INTERVAL;
+    }
+}
@@ -0,0 +1,84 @@
+use std::net::IpAddr;
+
+use traffic_utils::attribution::DomainMap;
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::flows::FlowTable;
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{Packet, PROTO_TCP, PROTO_UDP, TCP_RST};
+use traffic_utils::pipeline::DomainPipeline;
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn lan() -> LanMatcher {
+    LanMatcher::new(parse_cidr_list("192.168.1.0/24").unwrap(), Vec::new())
+}
+
+fn dns_response_a(name: &str, addr: [u8; 4]) -> Vec<u8> {
+    let mut msg = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+    for label in name.split('.') {
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.extend_from_slice(&[0, 0, 1, 0, 1]);
+    msg.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+    msg.extend_from_slice(&addr);
+    msg
+}
+
+fn answer(ts: f64, client: &str, name: &str, addr: [u8; 4]) -> Packet {
+    Packet::new(ts, ip("192.168.1.1"), ip(client), PROTO_UDP).with_transport(53, 40000, &dns_response_a(name, addr))
+}
+
+fn http(ts: f64, client: &str, host: &str) -> Packet {
+    let req = format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+    Packet::new(ts, ip(client), ip("151.101.1.1"), PROTO_TCP).with_transport(50000, 80, req.as_bytes())
+}
+
+// Como no bytes: os pacotes passam pelo DomainPipeline e o mapa aprende dos eventos
+fn learn(map: &mut DomainMap, pipeline: &mut DomainPipeline, packets: &[Packet]) {
+    for pkt in packets {
+        for ev in pipeline.process(pkt) { map.feed(&ev); }
+    }
+    for ev in pipeline.flush() { map.feed(&ev); }
+}
+
+#[test]
+fn dns_answers_map_remote_ip_to_domain() {
+    let mut map = DomainMap::new();
+    learn(&mut map, &mut DomainPipeline::new(lan()), &[answer(1.0, "192.168.1.10", "video.example.com", [151, 101, 1, 1])]);
+    assert_eq!(map.lookup(&ip("192.168.1.10"), &ip("151.101.1.1")), Some("video.example.com"));
+    // Outro cliente que não resolveu usa o que se sabe do IP
+    assert_eq!(map.lookup(&ip("192.168.1.20"), &ip("151.101.1.1")), Some("video.example.com"));
+    assert_eq!(map.lookup(&ip("192.168.1.10"), &ip("8.8.8.8")), None);
+}
+
+#[test]
+fn connection_host_beats_dns_on_shared_cdn_address() {
+    let mut map = DomainMap::new();
+    learn(&mut map, &mut DomainPipeline::new(lan()), &[
+        answer(1.0, "192.168.1.10", "a.example.com", [151, 101, 1, 1]),
+        http(1.1, "192.168.1.10", "b.example.com"),
+        answer(1.2, "192.168.1.10", "a.example.com", [151, 101, 1, 1]),
+    ]);
+    assert_eq!(map.lookup(&ip("192.168.1.10"), &ip("151.101.1.1")), Some("b.example.com"));
+}
+
+#[test]
+fn flows_carry_the_attributed_domain() {
+    let mut table = FlowTable::new(lan());
+    let mut pipeline = DomainPipeline::new(lan());
+    let devices = DeviceTable::new();
+    let data = Packet::new(1.5, ip("151.101.1.1"), ip("192.168.1.10"), PROTO_TCP).with_transport(443, 50001, &[0; 1200]);
+    // DNS sem conexão TLS/HTTP: o evento sai quando a janela do pipeline expira (próximo pacote com domínio)
+    let later = answer(7.0, "192.168.1.20", "other.example.com", [198, 51, 100, 1]);
+    let rst = Packet::new(8.0, ip("192.168.1.10"), ip("151.101.1.1"), PROTO_TCP).with_transport(50001, 443, &[]).with_flags(TCP_RST);
+    let mut done = Vec::new();
+    for pkt in [answer(1.0, "192.168.1.10", "video.example.com", [151, 101, 1, 1]), data, later, rst] {
+        for ev in pipeline.process(&pkt) { table.domains.feed(&ev); }
+        done.extend(table.process(&pkt, &devices));
+    }
+    assert_eq!(done[0].domain.as_deref(), Some("video.example.com"));
+    assert_eq!(done[0].bytes_in, 1200);
+}
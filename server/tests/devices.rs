@@ -0,0 +1,120 @@
+use std::net::IpAddr;
+
+use traffic_utils::capture::{PacketSource, SyntheticSource};
+use traffic_utils::counters::ByteCounter;
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{decode_ethernet, Packet, PROTO_ICMPV6, PROTO_TCP, PROTO_UDP};
+use traffic_utils::pipeline::DomainPipeline;
+
+const PHONE: [u8; 6] = [0x3c, 0x22, 0xfb, 0x10, 0x20, 0x30];
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn lan() -> LanMatcher {
+    LanMatcher::new(parse_cidr_list("192.168.1.0/24,fd00::/8").unwrap(), Vec::new())
+}
+
+// Quadro Ethernet com ARP reply de `sender`
+fn arp_frame(sender_mac: [u8; 6], sender_ip: [u8; 4]) -> Vec<u8> {
+    let mut f = vec![0xff; 6];
+    f.extend_from_slice(&sender_mac);
+    f.extend_from_slice(&[0x08, 0x06, 0, 1, 0x08, 0x00, 6, 4, 0, 2]);
+    f.extend_from_slice(&sender_mac);
+    f.extend_from_slice(&sender_ip);
+    f.extend_from_slice(&[0; 6]);
+    f.extend_from_slice(&[192, 168, 1, 1]);
+    f
+}
+
+fn arp(ts: f64, mac: [u8; 6], addr: [u8; 4]) -> Packet {
+    decode_ethernet(ts, &arp_frame(mac, addr)).unwrap()
+}
+
+fn traffic(ts: f64, client: &str, bytes: usize) -> Packet {
+    Packet::new(ts, ip(client), ip("8.8.8.8"), PROTO_UDP).with_transport(5000, 443, &vec![0; bytes])
+}
+
+#[test]
+fn arp_and_ndp_bind_ip_to_mac() {
+    let mut table = DeviceTable::new();
+    assert_eq!(table.feed(&arp(1.0, PHONE, [192, 168, 1, 50])), vec!["192.168.1.50".to_string()]);
+    assert!(table.feed(&arp(2.0, PHONE, [192, 168, 1, 50])).is_empty());
+
+    // Neighbor Advertisement com opção de MAC do alvo
+    let mut na = vec![136, 0, 0, 0, 0x60, 0, 0, 0];
+    na.extend_from_slice(&"fd00::50".parse::<std::net::Ipv6Addr>().unwrap().octets());
+    na.extend_from_slice(&[2, 1]);
+    na.extend_from_slice(&PHONE);
+    let mut pkt = Packet::new(3.0, ip("fd00::50"), ip("fd00::1"), PROTO_ICMPV6);
+    pkt.payload = na;
+    table.feed(&pkt);
+
+    assert_eq!(table.mac_of("fd00::50"), Some("3c:22:fb:10:20:30"));
+    assert_eq!(table.ips_of("3c:22:fb:10:20:30"), vec!["192.168.1.50".to_string(), "fd00::50".to_string()]);
+    assert_eq!(table.device_id("192.168.1.99"), "192.168.1.99");
+}
+
+#[test]
+fn bytes_follow_the_device_across_ip_changes() {
+    let mut counter = ByteCounter::new(lan());
+    counter.process(&traffic(1.0, "192.168.1.50", 100)); // antes do ARP: contado pelo IP
+    counter.process(&arp(2.0, PHONE, [192, 168, 1, 50]));
+    counter.process(&traffic(3.0, "192.168.1.50", 200));
+    // nova concessão
+    counter.process(&arp(4.0, PHONE, [192, 168, 1, 77]));
+    counter.process(&traffic(5.0, "192.168.1.77", 300));
+
+    assert_eq!(counter.stats.len(), 1);
+    let phone = &counter.stats["3c:22:fb:10:20:30"];
+    assert_eq!(phone.bytes_out, 600);
+    assert_eq!(phone.ip.as_deref(), Some("192.168.1.77"));
+}
+
+#[test]
+fn events_carry_the_client_mac() {
+    let http = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let packets = vec![
+        arp(1.0, PHONE, [192, 168, 1, 50]),
+        Packet::new(2.0, ip("192.168.1.50"), ip("93.184.216.34"), PROTO_TCP).with_transport(40000, 80, http),
+        Packet::new(3.0, ip("192.168.1.60"), ip("93.184.216.34"), PROTO_TCP).with_transport(40000, 80, http),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let mut source = SyntheticSource::new(packets);
+    let mut events = Vec::new();
+    while let Some(pkt) = source.next_packet().unwrap() {
+        events.extend(pipeline.process(&pkt));
+    }
+
+    let macs: Vec<_> = events.iter().map(|e| e.mac.as_deref()).collect();
+    assert_eq!(macs, vec![Some("3c:22:fb:10:20:30"), None]);
+}
+
+#[test]
+fn device_roaming_between_interfaces_keeps_being_counted() {
+    let lan = LanMatcher::new(parse_cidr_list("192.168.1.0/24,192.168.20.0/24").unwrap(), Vec::new());
+    let mut counter = ByteCounter::new(lan);
+    counter.segments.insert("br-lan", parse_cidr_list("192.168.1.0/24").unwrap());
+    counter.segments.insert("br-guest", parse_cidr_list("192.168.20.0/24").unwrap());
+    let id = "3c:22:fb:10:20:30";
+
+    counter.process(&arp(1.0, PHONE, [192, 168, 1, 50]).on_iface("br-lan"));
+    counter.process(&traffic(2.0, "192.168.1.50", 100).on_iface("br-lan"));
+    assert_eq!(counter.stats[id].iface.as_deref(), Some("br-lan"));
+
+    // Mudou para a rede de convidados: novo IP, mesmo MAC, outra bridge
+    counter.process(&arp(10.0, PHONE, [192, 168, 20, 30]).on_iface("br-guest"));
+    counter.process(&traffic(11.0, "192.168.20.30", 200).on_iface("br-guest"));
+    let phone = &counter.stats[id];
+    assert_eq!((phone.bytes_out, phone.packets_out), (300, 2));
+    assert_eq!((phone.iface.as_deref(), phone.ip.as_deref()), (Some("br-guest"), Some("192.168.20.30")));
+
+    // Cópia roteada para a outra bridge continua não contando duas vezes
+    let to_lan = |iface| Packet::new(12.0, ip("192.168.20.30"), ip("192.168.1.60"), PROTO_UDP).with_transport(5000, 80, &[0; 50]).on_iface(iface);
+    counter.process(&to_lan("br-guest"));
+    counter.process(&to_lan("br-lan"));
+    assert_eq!(counter.stats[id].bytes_out, 350);
+    assert_eq!(counter.stats["192.168.1.60"].bytes_in, 50);
+}
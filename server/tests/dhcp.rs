@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use traffic_utils::dhcp::{self, LeaseTable, DHCP_ACK, DHCP_REQUEST};
+use traffic_utils::packet::{Packet, PROTO_UDP};
+use traffic_utils::resolve_client_name;
+
+const MAC: [u8; 6] = [0xa4, 0x83, 0xe7, 0x01, 0x02, 0x03];
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn bootp(op: u8, yiaddr: [u8; 4], options: &[&[u8]]) -> Vec<u8> {
+    let mut m = vec![0u8; 240];
+    m[0] = op;
+    m[1] = 1;
+    m[2] = 6;
+    m[16..20].copy_from_slice(&yiaddr);
+    m[28..34].copy_from_slice(&MAC);
+    m[236..240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+    for opt in options {
+        m.extend_from_slice(opt);
+    }
+    m.push(255);
+    m
+}
+
+fn request() -> Packet {
+    let msg = bootp(1, [0; 4], &[&[53, 1, DHCP_REQUEST], &[50, 4, 192, 168, 1, 42], &[12, 7, b'i', b'p', b'h', b'o', b'n', b'e', 0], &[60, 4, b'M', b'S', b'F', b'T']]);
+    Packet::new(10.0, ip("0.0.0.0"), ip("255.255.255.255"), PROTO_UDP).with_transport(68, 67, &msg)
+}
+
+fn ack() -> Packet {
+    let msg = bootp(2, [192, 168, 1, 42], &[&[53, 1, DHCP_ACK], &[12, 6, b's', b'e', b'r', b'v', b'e', b'r']]);
+    Packet::new(10.1, ip("192.168.1.1"), ip("192.168.1.42"), PROTO_UDP).with_transport(67, 68, &msg)
+}
+
+#[test]
+fn parses_request_options() {
+    let msg = dhcp::parse(&request().payload).unwrap();
+    assert!(msg.from_client);
+    assert_eq!(msg.mac, "a4:83:e7:01:02:03");
+    assert_eq!(msg.hostname.as_deref(), Some("iphone"));
+    assert_eq!(msg.vendor_class.as_deref(), Some("MSFT"));
+    assert_eq!(msg.requested_ip, Some("192.168.1.42".parse().unwrap()));
+}
+
+#[test]
+fn ack_binds_ip_to_client_hostname() {
+    let mut table = LeaseTable::new();
+    assert!(table.feed(&request()));
+    assert!(table.hostnames().is_empty()); // sem ACK ainda não há IP confirmado
+    assert!(table.feed(&ack()));
+    assert!(!table.feed(&ack()));
+
+    let lease = &table.leases["a4:83:e7:01:02:03"];
+    assert_eq!((lease.ip.as_deref(), lease.hostname.as_deref()), (Some("192.168.1.42"), Some("iphone")));
+
+    let manual: HashMap<String, String> = [("192.168.1.10".to_string(), "nas".to_string())].into();
+    let learned = table.hostnames();
+    assert_eq!(resolve_client_name("192.168.1.42", None, &manual, &learned), "192.168.1.42 (iphone)");
+    assert_eq!(resolve_client_name("192.168.1.10", None, &manual, &learned), "192.168.1.10 (nas)");
+    assert_eq!(resolve_client_name("192.168.1.99", None, &manual, &learned), "192.168.1.99 (192.168.1.99)");
+
+    // Entrada por MAC no mapa manual vale em qualquer IP do aparelho
+    let by_mac: HashMap<String, String> = [("a4:83:e7:01:02:03".to_string(), "celular".to_string())].into();
+    assert_eq!(resolve_client_name("192.168.1.42", Some("a4:83:e7:01:02:03"), &by_mac, &learned), "192.168.1.42 (celular)");
+}
+
+#[test]
+fn leases_survive_save_and_load() {
+    let mut table = LeaseTable::new();
+    table.feed(&request());
+    table.feed(&ack());
+    let path = std::env::temp_dir().join(format!("dhcp-leases-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    table.save(path);
+
+    let loaded = LeaseTable::load(path);
+    let _ = std::fs::remove_file(path);
+    assert_eq!(loaded.leases, table.leases);
+    assert!(LeaseTable::load("/nonexistent/leases.json").leases.is_empty());
+}
@@ -0,0 +1,95 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use traffic_utils::dns::{self, RecordData, RCODE_NXDOMAIN, TYPE_A, TYPE_AAAA, TYPE_CNAME, TYPE_HTTPS};
+
+fn name(n: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for label in n.split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+// Registro com o nome comprimido apontando para a pergunta (offset 12)
+fn answer(rtype: u16, ttl: u32, rdata: &[u8]) -> Vec<u8> {
+    let mut out = vec![0xc0, 12];
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&[0, 1]);
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+    out
+}
+
+fn response(qname: &str, qtype: u16, rcode: u8, answers: &[Vec<u8>]) -> Vec<u8> {
+    let mut msg = vec![0xab, 0xcd, 0x81, 0x80 | rcode, 0, 1, 0, answers.len() as u8, 0, 0, 0, 0];
+    msg.extend(name(qname));
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&[0, 1]);
+    for a in answers {
+        msg.extend_from_slice(a);
+    }
+    msg
+}
+
+#[test]
+fn follows_cname_chain_to_addresses() {
+    // www.example.com CNAME edge.cdn.net; edge.cdn.net A 203.0.113.7
+    let cname_target = name("edge.cdn.net");
+    let cname = answer(TYPE_CNAME, 300, &cname_target);
+    let target_off = 12 + name("www.example.com").len() + 4 + 12;
+    let mut a = vec![0xc0, target_off as u8];
+    a.extend_from_slice(&TYPE_A.to_be_bytes());
+    a.extend_from_slice(&[0, 1, 0, 0, 0, 60, 0, 4, 203, 0, 113, 7]);
+    let aaaa = {
+        let mut r = vec![0xc0, target_off as u8];
+        r.extend_from_slice(&TYPE_AAAA.to_be_bytes());
+        r.extend_from_slice(&[0, 1, 0, 0, 0, 30, 0, 16]);
+        r.extend_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets());
+        r
+    };
+    let msg = dns::parse(&response("www.example.com", TYPE_A, 0, &[cname, a, aaaa])).unwrap();
+
+    assert!(msg.is_response);
+    assert_eq!(msg.id, 0xabcd);
+    assert_eq!(msg.query_name(), Some("www.example.com"));
+    assert_eq!(msg.answers[0].data, RecordData::Cname("edge.cdn.net".to_string()));
+    assert_eq!(msg.resolved_ips(), vec![
+        IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+    ]);
+    assert_eq!(msg.min_ttl(), Some(30));
+}
+
+#[test]
+fn https_record_hints_and_alpn() {
+    // priority 1, target ".", alpn=h3,h2, ipv4hint=198.51.100.1
+    let mut rdata = vec![0, 1, 0];
+    rdata.extend_from_slice(&[0, 1, 0, 6, 2, b'h', b'3', 2, b'h', b'2']);
+    rdata.extend_from_slice(&[0, 4, 0, 4, 198, 51, 100, 1]);
+    let msg = dns::parse(&response("svc.example", TYPE_HTTPS, 0, &[answer(TYPE_HTTPS, 120, &rdata)])).unwrap();
+
+    match &msg.answers[0].data {
+        RecordData::Svcb { priority, alpn, .. } => {
+            assert_eq!(*priority, 1);
+            assert_eq!(alpn, &vec!["h3".to_string(), "h2".to_string()]);
+        }
+        other => panic!("esperado SVCB, veio {:?}", other),
+    }
+    assert_eq!(msg.resolved_ips(), vec![IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))]);
+}
+
+#[test]
+fn nxdomain_has_no_addresses() {
+    let msg = dns::parse(&response("nope.example", TYPE_A, RCODE_NXDOMAIN, &[])).unwrap();
+    assert_eq!(msg.rcode, RCODE_NXDOMAIN);
+    assert!(msg.resolved_ips().is_empty());
+}
+
+#[test]
+fn truncated_message_is_rejected() {
+    let full = response("www.example.com", TYPE_A, 0, &[answer(TYPE_A, 60, &[1, 2, 3, 4])]);
+    assert!(dns::parse(&full[..full.len() - 2]).is_none());
+}
@@ -0,0 +1,140 @@
+use traffic_utils::eventdb::{BatchWriter, EventDb, EventQuery};
+use traffic_utils::events::{LogEvent, EVENT_VERSION, FLAG_VIA_DNS};
+
+fn temp_db(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{}-{}.db", name, std::process::id()));
+    for ext in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), ext));
+    }
+    path.to_str().unwrap().to_string()
+}
+
+fn event(ts: f64, client: &str, mac: Option<&str>, domain: &str) -> LogEvent {
+    LogEvent {
+        v: EVENT_VERSION,
+        ts,
+        client: client.to_string(),
+        name: None,
+        domain: domain.to_string(),
+        remote: "142.250.0.1".to_string(),
+        source: "TLS".to_string(),
+        iface: Some("br-lan".to_string()),
+        mac: mac.map(str::to_string),
+        alpn: None,
+        tls: Some("1.3".to_string()),
+        ja3: None,
+        ja4: None,
+        answers: Vec::new(),
+        ttl: None,
+        rcode: None,
+        flags: Vec::new(),
+    }
+}
+
+fn domains(events: &[LogEvent]) -> Vec<&str> {
+    events.iter().map(|e| e.domain.as_str()).collect()
+}
+
+const DAY: f64 = 86400.0;
+
+#[test]
+fn filters_by_time_device_and_domain_across_days() {
+    let path = temp_db("events-filter");
+    let mut db = EventDb::open(&path).unwrap();
+    let mut delayed = event(3.0 * DAY, "192.168.1.20", None, "ads.example.com");
+    delayed.flags = vec![FLAG_VIA_DNS.to_string()];
+    delayed.answers = vec!["203.0.113.9".to_string(), "203.0.113.10".to_string()];
+    delayed.ttl = Some(60);
+    db.insert_batch(&[
+        event(DAY, "192.168.1.10", None, "old.example.com"), // antes do MAC ser conhecido
+        event(2.0 * DAY, "192.168.1.10", Some("3c:22:fb:10:20:30"), "www.youtube.com"),
+        event(2.5 * DAY, "192.168.1.99", Some("3C:22:FB:10:20:30"), "i.ytimg.com"), // mesmo aparelho, outro IP
+        event(2.6 * DAY, "192.168.1.10", Some("aa:bb:cc:dd:ee:ff"), "other.com"), // IP reaproveitado
+        delayed,
+    ]).unwrap();
+
+    let all = db.query(&EventQuery { limit: 100, ..Default::default() }).unwrap();
+    assert_eq!(domains(&all), vec!["ads.example.com", "other.com", "i.ytimg.com", "www.youtube.com", "old.example.com"]);
+    assert_eq!(all[0], LogEvent {
+        flags: vec![FLAG_VIA_DNS.to_string()],
+        answers: vec!["203.0.113.9".to_string(), "203.0.113.10".to_string()],
+        ttl: Some(60),
+        ..event(3.0 * DAY, "192.168.1.20", None, "ads.example.com")
+    });
+
+    let device = EventQuery { mac: Some("3c:22:fb:10:20:30".to_string()), ips: vec!["192.168.1.10".to_string()], limit: 100, ..Default::default() };
+    assert_eq!(domains(&db.query(&device).unwrap()), vec!["i.ytimg.com", "www.youtube.com", "old.example.com"]);
+    let two_days = EventQuery { since: Some(2.0 * DAY), until: Some(3.0 * DAY), ..device };
+    assert_eq!(domains(&db.query(&two_days).unwrap()), vec!["i.ytimg.com", "www.youtube.com"]);
+
+    let by_domain = EventQuery { domain: Some("example.com".to_string()), limit: 100, ..Default::default() };
+    assert_eq!(domains(&db.query(&by_domain).unwrap()), vec!["ads.example.com", "old.example.com"]);
+    // '_' não vira curinga do LIKE
+    let literal = EventQuery { domain: Some("_xample.com".to_string()), limit: 100, ..Default::default() };
+    assert!(db.query(&literal).unwrap().is_empty());
+}
+
+#[test]
+fn pages_with_offset_and_batches_inserts() {
+    let path = temp_db("events-batch");
+    let mut writer = BatchWriter::new(EventDb::open(&path).unwrap());
+    for i in 0..10 {
+        writer.push(event(1000.0 + i as f64, "192.168.1.10", None, &format!("d{}.com", i))).unwrap();
+    }
+    let reader = EventDb::open_read_only(&path).unwrap();
+    // Ainda no lote: nada gravado
+    assert!(reader.first_ts().unwrap().is_none());
+    writer.flush_if_due(1010.0).unwrap();
+    assert_eq!(reader.first_ts().unwrap(), Some(1000.0));
+
+    let page = |offset| reader.query(&EventQuery { offset, limit: 4, ..Default::default() }).unwrap();
+    assert_eq!(domains(&page(0)), vec!["d9.com", "d8.com", "d7.com", "d6.com"]);
+    assert_eq!(domains(&page(8)), vec!["d1.com", "d0.com"]);
+}
+
+#[test]
+fn prunes_events_outside_retention() {
+    let path = temp_db("events-prune");
+    let writer = {
+        let mut w = BatchWriter::new(EventDb::open(&path).unwrap());
+        w.push(event(DAY, "192.168.1.10", Some("3C:22:FB:10:20:30"), "old.com")).unwrap();
+        w.push(event(5.0 * DAY, "192.168.1.10", Some("3C:22:FB:10:20:30"), "new.com")).unwrap();
+        w.flush().unwrap();
+        w
+    };
+    assert_eq!(writer.prune(2.0 * DAY).unwrap(), 1);
+
+    let reader = EventDb::open_read_only(&path).unwrap();
+    let left = reader.query(&EventQuery { limit: 100, ..Default::default() }).unwrap();
+    assert_eq!(domains(&left), vec!["new.com"]);
+    // MAC sai do banco normalizado
+    assert_eq!(left[0].mac.as_deref(), Some("3c:22:fb:10:20:30"));
+}
+
+#[test]
+fn old_database_gains_indexed_reversed_domains() {
+    let path = temp_db("events-migrate");
+    {
+        // Schema da primeira versão, sem answers/ttl/rcode/rdomain
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, ts REAL NOT NULL, client TEXT NOT NULL, name TEXT, mac TEXT,
+                domain TEXT NOT NULL, remote TEXT NOT NULL, source TEXT NOT NULL, iface TEXT, alpn TEXT, tls TEXT,
+                ja3 TEXT, ja4 TEXT, delayed_dns INTEGER NOT NULL DEFAULT 0);
+            CREATE INDEX events_domain_ts ON events(domain, ts);
+            INSERT INTO events (ts, client, domain, remote, source) VALUES (1.0, '192.168.1.10', 'i.ytimg.com', '142.250.0.1', 'TLS');
+        ").unwrap();
+    }
+    let mut db = EventDb::open(&path).unwrap();
+    db.insert_batch(&[event(2.0, "192.168.1.10", None, "ytimg.com"), event(3.0, "192.168.1.10", None, "notytimg.com")]).unwrap();
+
+    let by_domain = EventQuery { domain: Some("ytimg.com".to_string()), limit: 100, ..Default::default() };
+    assert_eq!(domains(&db.query(&by_domain).unwrap()), vec!["ytimg.com", "i.ytimg.com"]);
+
+    // Busca por subdomínio usa o índice, sem varrer a tabela
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let mut stmt = conn.prepare(
+        "EXPLAIN QUERY PLAN SELECT id FROM events WHERE rdomain = 'com.ytimg' OR (rdomain >= 'com.ytimg.' AND rdomain < 'com.ytimg/')").unwrap();
+    let plan: Vec<String> = stmt.query_map([], |r| r.get(3)).unwrap().map(Result::unwrap).collect();
+    assert!(plan.iter().any(|p| p.contains("events_rdomain_ts")) && !plan.iter().any(|p| p.starts_with("SCAN")), "{:?}", plan);
+}
@@ -0,0 +1,75 @@
+use traffic_utils::events::{jsonl_path, LogEvent, EVENT_VERSION, FLAG_VIA_DNS};
+use traffic_utils::logline::LogLine;
+use traffic_utils::parse_log_line;
+use traffic_utils::pipeline::DomainEvent;
+
+fn event(delayed: bool) -> DomainEvent {
+    DomainEvent {
+        ts: 1_764_620_960.0,
+        client: "192.168.1.10".to_string(),
+        domain: "www.youtube.com".to_string(),
+        remote: "142.250.0.1".to_string(),
+        source: "TLS",
+        delayed,
+        answers: Vec::new(),
+        dns_ttl: None,
+        dns_rcode: None,
+        alpn: Some("h2,http/1.1".to_string()),
+        tls_version: Some("1.3".to_string()),
+        ja3: None,
+        ja4: None,
+        iface: Some("br-lan".to_string()),
+        mac: Some("3c:22:fb:10:20:30".to_string()),
+    }
+}
+
+#[test]
+fn jsonl_event_reads_like_a_text_line() {
+    let line = LogEvent::from_event(&event(false), Some("tv")).to_line();
+    assert!(line.starts_with(&format!("{{\"v\":{},", EVENT_VERSION)));
+    assert!(!line.contains("ja3") && !line.contains("flags"));
+
+    let entry = parse_log_line(&line).unwrap();
+    assert_eq!((entry.client_ip.as_str(), entry.client_name.as_str()), ("192.168.1.10", "tv"));
+    assert_eq!((entry.domain.as_str(), entry.remote_ip.as_str(), entry.source.as_str()), ("www.youtube.com", "142.250.0.1", "TLS"));
+    assert_eq!(entry.tls_version.as_deref(), Some("1.3"));
+    assert_eq!(entry.mac.as_deref(), Some("3c:22:fb:10:20:30"));
+    // `raw` é a linha texto, igual à do LOG_FORMAT=text
+    assert_eq!(entry.raw, LogLine::from_event(&event(false), Some("tv")).format());
+}
+
+#[test]
+fn delayed_dns_becomes_a_flag_and_unknown_name_falls_back_to_ip() {
+    let line = LogEvent::from_event(&event(true), None).to_line();
+    let entry = parse_log_line(&line).unwrap();
+    assert_eq!(entry.flags, vec![FLAG_VIA_DNS]);
+    assert_eq!(entry.client_name, "192.168.1.10");
+    // Campos novos de uma versão futura não quebram a leitura
+    let newer = line.replacen(&format!("\"v\":{}", EVENT_VERSION), "\"v\":99,\"extra\":true", 1);
+    assert!(parse_log_line(&newer).is_some());
+    assert!(parse_log_line("{\"v\":1,\"ts\":").is_none());
+}
+
+#[test]
+fn resolved_addresses_are_kept_in_the_event() {
+    let resolved = DomainEvent {
+        source: "DNS",
+        remote: "142.250.0.1".to_string(),
+        answers: vec!["142.250.0.1".parse().unwrap(), "2800:3f0:4001::65".parse().unwrap()],
+        dns_ttl: Some(300),
+        alpn: None,
+        tls_version: None,
+        ..event(true)
+    };
+    let line = LogEvent::from_event(&resolved, None).to_line();
+    let entry = parse_log_line(&line).unwrap();
+    assert_eq!(entry.answers, vec!["142.250.0.1", "2800:3f0:4001::65"]);
+    assert_eq!((entry.dns_ttl, entry.dns_rcode), (Some(300), None));
+    assert!(!LogEvent::from_event(&event(false), None).to_line().contains("answers"));
+}
+
+#[test]
+fn jsonl_file_sits_next_to_the_text_log() {
+    assert_eq!(jsonl_path("/var/log/traffic-domains/traffic-domains.log"), "/var/log/traffic-domains/traffic-domains.jsonl");
+    assert_eq!(jsonl_path("/tmp/domains"), "/tmp/domains.jsonl");
+}
@@ -0,0 +1,117 @@
+use std::net::IpAddr;
+
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::fingerprint::{guess_from_dhcp, guess_from_user_agent, FingerprintTable};
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{decode_ethernet, decode_raw_ip, Packet, PROTO_TCP, PROTO_UDP};
+
+const MAC: [u8; 6] = [0xa4, 0x83, 0xe7, 0x01, 0x02, 0x03];
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn lan() -> LanMatcher {
+    LanMatcher::new(parse_cidr_list("192.168.1.0/24").unwrap(), Vec::new())
+}
+
+// SYN IPv4 de 192.168.1.42 com as opções na ordem do macOS/iOS
+fn mac_syn(ttl: u8) -> Packet {
+    let opts = [2, 4, 0x05, 0xb4, 1, 3, 3, 6, 1, 1, 8, 10, 0, 0, 0, 1, 0, 0, 0, 0, 4, 2, 0, 0];
+    let mut tcp = vec![0xc3, 0x50, 0x01, 0xbb, 0, 0, 0, 1, 0, 0, 0, 0, 0xb0, 0x02, 0xff, 0xff, 0, 0, 0, 0];
+    tcp.extend_from_slice(&opts);
+    let total = 20 + tcp.len();
+    let mut p = vec![0x45, 0, 0, total as u8, 0, 0, 0x40, 0, ttl, 6, 0, 0, 192, 168, 1, 42, 17, 253, 144, 10];
+    p.extend_from_slice(&tcp);
+    decode_raw_ip(1.0, &p).unwrap()
+}
+
+fn dhcp_discover(params: &[u8]) -> Packet {
+    let mut m = vec![0u8; 240];
+    m[0] = 1;
+    m[1] = 1;
+    m[2] = 6;
+    m[28..34].copy_from_slice(&MAC);
+    m[236..240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+    m.extend_from_slice(&[53, 1, 1, 55, params.len() as u8]);
+    m.extend_from_slice(params);
+    m.push(255);
+    Packet::new(2.0, ip("0.0.0.0"), ip("255.255.255.255"), PROTO_UDP).with_transport(68, 67, &m)
+}
+
+fn http_get(ua: &str) -> Packet {
+    let req = format!("GET / HTTP/1.1\r\nHost: example.com\r\nUser-Agent: {}\r\n\r\n", ua);
+    Packet::new(3.0, ip("192.168.1.42"), ip("93.184.216.34"), PROTO_TCP).with_transport(50000, 80, req.as_bytes())
+}
+
+#[test]
+fn syn_options_and_ttl_give_os_family() {
+    let syn = mac_syn(63).syn.unwrap();
+    assert_eq!(syn.mss, Some(1460));
+    assert_eq!(syn.wscale, Some(6));
+    assert_eq!(syn.options, "mss,nop,ws,nop,nop,ts,sok,eol");
+
+    let mut table = FingerprintTable::new();
+    let devices = DeviceTable::new();
+    table.feed(&mac_syn(63), &lan(), &devices);
+    assert_eq!(table.get("192.168.1.42").unwrap().best.os.as_deref(), Some("macOS/iOS"));
+    table.feed(&mac_syn(127), &lan(), &devices);
+    assert_eq!(table.get("192.168.1.42").unwrap().best.os.as_deref(), Some("Windows"));
+}
+
+#[test]
+fn user_agent_beats_dhcp_and_syn() {
+    assert_eq!(guess_from_dhcp(&[1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252]).unwrap().os.as_deref(), Some("Windows"));
+    let tv = guess_from_user_agent("Mozilla/5.0 (SMART-TV; Linux; Tizen 6.0) AppleWebKit/537.36").unwrap();
+    assert_eq!((tv.os.as_deref(), tv.device.as_deref()), (Some("Tizen"), Some("tv")));
+
+    let mut table = FingerprintTable::new();
+    let devices = DeviceTable::new();
+    table.feed(&mac_syn(64), &lan(), &devices);
+    table.feed(&http_get("Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15"), &lan(), &devices);
+    let fp = table.get("192.168.1.42").unwrap();
+    assert_eq!((fp.best.os.as_deref(), fp.best.device.as_deref()), (Some("iOS"), Some("celular")));
+    assert_eq!(fp.evidence["syn"].signature, "64:65535:mss,nop,ws,nop,nop,ts,sok,eol");
+}
+
+#[test]
+fn fingerprints_follow_device_mac_and_persist() {
+    let mut table = FingerprintTable::new();
+    let mut devices = DeviceTable::new();
+    table.feed(&mac_syn(64), &lan(), &devices);
+    table.feed(&dhcp_discover(&[1, 121, 3, 6, 15, 119, 252, 95, 44, 46]), &lan(), &devices);
+
+    // ARP vincula o IP ao MAC: o que estava pelo IP passa para o MAC
+    let mut arp = vec![0xff; 6];
+    arp.extend_from_slice(&MAC);
+    arp.extend_from_slice(&[0x08, 0x06, 0, 1, 0x08, 0x00, 6, 4, 0, 2]);
+    arp.extend_from_slice(&MAC);
+    arp.extend_from_slice(&[192, 168, 1, 42, 0, 0, 0, 0, 0, 0, 192, 168, 1, 1]);
+    devices.feed(&decode_ethernet(4.0, &arp).unwrap());
+    table.feed(&mac_syn(64), &lan(), &devices);
+
+    assert!(table.get("192.168.1.42").is_none());
+    let fp = table.get("a4:83:e7:01:02:03").unwrap();
+    assert_eq!(fp.evidence.keys().cloned().collect::<Vec<_>>(), vec!["dhcp".to_string(), "syn".to_string()]);
+    assert_eq!((fp.best.os.as_deref(), fp.best.device.as_deref()), (Some("macOS"), Some("computador")));
+
+    let path = std::env::temp_dir().join(format!("fingerprints-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    table.save_if_dirty(path);
+    assert_eq!(FingerprintTable::load(path).clients, table.clients);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn new_ja4_on_known_device_is_flagged() {
+    let mut table = FingerprintTable::new();
+    let devices = DeviceTable::new();
+    assert!(!table.record_tls("192.168.1.42", &devices, "aaa", "t13d1516h2_x_y", "www.google.com", 1.0));
+    assert!(!table.record_tls("192.168.1.42", &devices, "aaa", "t13d1516h2_x_y", "youtube.com", 2.0));
+    assert!(table.record_tls("192.168.1.42", &devices, "bbb", "t12i0306__z_w", "iot.example", 3.0));
+
+    let tls = &table.get("192.168.1.42").unwrap().tls;
+    assert_eq!(tls["t13d1516h2_x_y"].count, 2);
+    assert_eq!(tls["t13d1516h2_x_y"].domains.len(), 2);
+    assert_eq!(tls["t12i0306__z_w"].ja3, "bbb");
+}
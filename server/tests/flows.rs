@@ -0,0 +1,115 @@
+use std::net::IpAddr;
+
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::flows::{self, FlowTable};
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{Packet, PROTO_TCP, PROTO_UDP, TCP_ACK, TCP_FIN, TCP_RST, TCP_SYN};
+use traffic_utils::rotation::{apply_retention, RotationConfig};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn lan() -> LanMatcher {
+    LanMatcher::new(parse_cidr_list("192.168.1.0/24").unwrap(), Vec::new())
+}
+
+fn out(ts: f64, flags: u8, bytes: usize) -> Packet {
+    Packet::new(ts, ip("192.168.1.10"), ip("142.250.0.1"), PROTO_TCP).with_transport(50000, 443, &vec![0; bytes]).with_flags(flags)
+}
+
+fn back(ts: f64, flags: u8, bytes: usize) -> Packet {
+    Packet::new(ts, ip("142.250.0.1"), ip("192.168.1.10"), PROTO_TCP).with_transport(443, 50000, &vec![0; bytes]).with_flags(flags)
+}
+
+#[test]
+fn tcp_flow_closes_after_fin_from_both_sides() {
+    let mut table = FlowTable::new(lan());
+    let devices = DeviceTable::new();
+    let mut done = Vec::new();
+    for pkt in [out(1.0, TCP_SYN, 0), back(1.1, TCP_SYN | TCP_ACK, 0), out(1.2, TCP_ACK, 500), back(1.3, TCP_ACK, 3000),
+                out(2.0, TCP_FIN | TCP_ACK, 0), back(2.1, TCP_FIN | TCP_ACK, 0)] {
+        done.extend(table.process(&pkt, &devices));
+    }
+    assert_eq!(done.len(), 1);
+    let f = &done[0];
+    assert_eq!((f.client.as_str(), f.client_port, f.remote.as_str(), f.remote_port), ("192.168.1.10", 50000, "142.250.0.1", 443));
+    assert_eq!((f.packets_out, f.packets_in, f.bytes_out, f.bytes_in), (3, 3, 500, 3000));
+    assert_eq!((f.start, f.end, f.end_reason.as_deref()), (1.0, 2.1, Some("fin")));
+    assert!(table.is_empty());
+}
+
+#[test]
+fn rst_and_idle_timeout_end_flows() {
+    let mut table = FlowTable::new(lan());
+    let devices = DeviceTable::new();
+    // Resposta vista primeiro (captura começou no meio): o cliente continua sendo o da LAN
+    assert!(table.process(&back(1.0, TCP_ACK, 100), &devices).is_empty());
+    let done = table.process(&out(1.5, TCP_RST, 0), &devices);
+    assert_eq!(done[0].client, "192.168.1.10");
+    assert_eq!(done[0].end_reason.as_deref(), Some("rst"));
+
+    let dns = Packet::new(10.0, ip("192.168.1.10"), ip("1.1.1.1"), PROTO_UDP).with_transport(5353, 53, &[0; 40]);
+    table.process(&dns, &devices);
+    assert_eq!(table.active().len(), 1);
+    let later = Packet::new(100.0, ip("192.168.1.20"), ip("1.1.1.1"), PROTO_UDP).with_transport(6000, 53, &[0; 40]);
+    let done = table.process(&later, &devices);
+    assert_eq!(done.len(), 1);
+    assert_eq!((done[0].remote_port, done[0].end_reason.as_deref()), (53, Some("timeout")));
+}
+
+#[test]
+fn flows_follow_client_segment_and_skip_ignored_clients() {
+    let mut table = FlowTable::new(lan());
+    table.segments.insert("br-lan", parse_cidr_list("192.168.1.0/28").unwrap());
+    table.segments.insert("br-guest", parse_cidr_list("192.168.1.48/28").unwrap());
+    table.ignore_clients.insert("192.168.1.11".to_string());
+    let devices = DeviceTable::new();
+    // Primeira cópia vista na bridge de outro segmento (roteada): não abre nem conta
+    table.process(&out(1.0, TCP_SYN, 0).on_iface("br-guest"), &devices);
+    assert!(table.is_empty());
+    for pkt in [out(1.0, TCP_SYN, 0).on_iface("br-lan"), back(1.1, TCP_ACK, 200).on_iface("br-guest"), back(1.1, TCP_ACK, 200).on_iface("br-lan")] {
+        table.process(&pkt, &devices);
+    }
+    let ignored = Packet::new(2.0, ip("192.168.1.11"), ip("1.1.1.1"), PROTO_UDP).with_transport(6000, 53, &[0; 40]);
+    table.process(&ignored, &devices);
+
+    let active = table.active();
+    assert_eq!(active.len(), 1);
+    assert_eq!((active[0].iface.as_deref(), active[0].packets_out, active[0].packets_in, active[0].bytes_in), (Some("br-lan"), 1, 1, 200));
+}
+
+#[test]
+fn finished_flows_round_trip_through_daily_file() {
+    let dir = std::env::temp_dir().join(format!("flows-{}", std::process::id()));
+    let dir = dir.to_str().unwrap();
+    let mut table = FlowTable::new(lan());
+    let devices = DeviceTable::new();
+    table.process(&out(1_700_000_000.0, TCP_SYN, 0), &devices);
+    let done = table.process(&back(1_700_000_001.0, TCP_RST, 0), &devices);
+    flows::append_flows(dir, &done);
+
+    let files: Vec<_> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(flows::read_flows(&files[0]), done);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn old_flow_days_follow_log_retention() {
+    let dir = std::env::temp_dir().join(format!("flows-retention-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for date in ["2025-11-01", "2025-11-25", "2025-11-30"] {
+        std::fs::write(flows::flows_file(dir.to_str().unwrap(), date), "{}\n").unwrap();
+    }
+    std::fs::write(flows::active_flows_file(dir.to_str().unwrap()), "[]").unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let config = RotationConfig { keep_days: Some(10), ..Default::default() };
+    apply_retention(flows::flow_days(dir), &config, "2025-12-01");
+    let kept: Vec<String> = flows::flow_days(dir).into_iter().map(|d| d.0).collect();
+    assert_eq!(kept, vec!["2025-11-25", "2025-11-30"]);
+    assert!(flows::active_flows_file(dir).exists());
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,56 @@
+use std::net::IpAddr;
+
+use traffic_utils::lan::{parse_cidr_list, Cidr, LanMatcher};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn cidr_matches_only_its_family_and_prefix() {
+    let net: Cidr = "192.168.1.0/24".parse().unwrap();
+    assert!(net.contains(&ip("192.168.1.200")));
+    assert!(!net.contains(&ip("192.168.10.1"))); // "^192\.168\.1" casava esse também
+    assert!(!net.contains(&ip("::ffff:192.168.1.5")));
+
+    let any: Cidr = "0.0.0.0/0".parse().unwrap();
+    assert!(any.contains(&ip("8.8.8.8")));
+    let host: Cidr = "fd00::1".parse().unwrap();
+    assert_eq!(host.to_string(), "fd00::1/128");
+}
+
+#[test]
+fn invalid_entries_are_rejected() {
+    assert!(parse_cidr_list("192.168.1.0/33").is_err());
+    assert!(parse_cidr_list("fd00::/129").is_err());
+    assert!(parse_cidr_list("192.168.1.0/24, lan").is_err());
+    assert_eq!(parse_cidr_list(" 10.0.0.0/8,\n fd00::/8 ").unwrap().len(), 2);
+}
+
+#[test]
+fn exclusions_win_over_lan_networks() {
+    let lan = LanMatcher::new(
+        parse_cidr_list("192.168.1.0/24,192.168.30.0/24,fd00::/8").unwrap(),
+        parse_cidr_list("192.168.1.1,192.168.30.0/25").unwrap(),
+    );
+    assert!(lan.is_lan(&ip("192.168.1.10")));
+    assert!(!lan.is_lan(&ip("192.168.1.1")));
+    assert!(!lan.is_lan(&ip("192.168.30.5")));
+    assert!(lan.is_lan(&ip("192.168.30.200")));
+    assert!(lan.is_lan(&ip("fd12::1")));
+    assert!(!lan.is_lan(&ip("2001:db8::1")));
+}
+
+#[test]
+fn legacy_lan_regex_is_not_silently_replaced() {
+    // Único teste deste binário que mexe no ambiente
+    std::env::set_var("LAN_REGEX", "^10\\.0\\.");
+    std::env::remove_var("LAN_NETS");
+    let err = std::panic::catch_unwind(LanMatcher::from_env).unwrap_err();
+    assert!(err.downcast_ref::<String>().unwrap().contains("LAN_NETS"));
+
+    std::env::set_var("LAN_NETS", "10.0.0.0/8");
+    assert!(LanMatcher::from_env().is_lan(&ip("10.0.3.4")));
+    std::env::remove_var("LAN_REGEX");
+    std::env::remove_var("LAN_NETS");
+}
@@ -0,0 +1,78 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use proptest::option;
+use proptest::prelude::*;
+use traffic_utils::logline::{format_timestamp, LogLine};
+use traffic_utils::parse_log_line;
+
+fn any_ip() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<[u8; 4]>().prop_map(|b| Ipv4Addr::from(b).to_string()),
+        any::<[u16; 8]>().prop_map(|s| Ipv6Addr::from(s).to_string()),
+    ]
+}
+
+fn any_line() -> impl Strategy<Value = LogLine> {
+    let token = "[A-Za-z0-9,./:_-]{1,24}";
+    (
+        (0i64..4_000_000_000).prop_map(|ts| format_timestamp(ts as f64)),
+        any_ip(),
+        "[A-Za-z0-9._' -]{0,20}",
+        "[a-z0-9-]{1,12}(\\.[a-z0-9-]{1,12}){0,3}",
+        prop_oneof![any_ip(), Just(String::new())],
+        prop_oneof![Just(""), Just("DNS"), Just("TLS"), Just("HTTP"), Just("QUIC")],
+        any::<bool>(),
+        (option::of(token), option::of(token), option::of("[0-9a-f]{32}"), option::of("[tq][0-9a-z_]{10,36}"), option::of("[a-z0-9.-]{1,15}"), option::of("([0-9a-f]{2}:){5}[0-9a-f]{2}")),
+        (prop::collection::vec(any_ip(), 0..4), option::of(any::<u32>()), option::of("[A-Z]{4,8}")),
+    ).prop_map(|(timestamp, client_ip, name, domain, remote_ip, source, delayed_dns, (alpn, tls_version, ja3, ja4, iface, mac), (answers, dns_ttl, dns_rcode))| LogLine {
+        // Nome vazio ou só espaços sai como o próprio IP
+        client_name: if name.trim().is_empty() { client_ip.clone() } else { name.trim().to_string() },
+        timestamp, client_ip, domain, remote_ip, source: source.to_string(), delayed_dns,
+        alpn, tls_version, ja3, ja4, iface, mac, answers, dns_ttl, dns_rcode,
+    })
+}
+
+proptest! {
+    #[test]
+    fn every_written_line_parses_back(line in any_line()) {
+        let text = line.format();
+        prop_assert_eq!(LogLine::parse(&text), Some(line.clone()));
+        let entry = parse_log_line(&text).unwrap();
+        prop_assert_eq!(entry.delayed_dns, line.delayed_dns);
+        prop_assert_eq!(entry.domain, line.domain);
+    }
+
+    // Nome aprendido na rede pode ter qualquer coisa: a linha ainda tem que voltar
+    #[test]
+    fn arbitrary_names_and_values_never_break_the_line(name in "\\PC{0,30}", alpn in "\\PC{1,20}", line in any_line()) {
+        let line = LogLine { client_name: name, alpn: Some(alpn), ..line };
+        let parsed = LogLine::parse(&line.format());
+        prop_assert!(parsed.is_some());
+        let parsed = parsed.unwrap();
+        prop_assert_eq!((parsed.client_ip, parsed.remote_ip, parsed.delayed_dns), (line.client_ip, line.remote_ip, line.delayed_dns));
+    }
+}
+
+#[test]
+fn delayed_dns_line_is_no_longer_dropped() {
+    let line = "[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → ads.example.com (via DNS: 192.168.1.1) | fonte=DNS | iface=br-lan";
+    let entry = parse_log_line(line).unwrap();
+    assert!(entry.delayed_dns);
+    assert_eq!(entry.flags, vec!["via_dns"]);
+    assert_eq!((entry.domain.as_str(), entry.remote_ip.as_str()), ("ads.example.com", "192.168.1.1"));
+    assert_eq!(entry.iface.as_deref(), Some("br-lan"));
+}
+
+#[test]
+fn resolved_dns_line_is_labeled_apart_from_the_resolver() {
+    let line = "[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → cdn.example (resolvido: 203.0.113.9) | fonte=DNS | iface=br-lan | answers=203.0.113.9,2001:db8::9 | ttl=60";
+    let entry = parse_log_line(line).unwrap();
+    assert!(entry.delayed_dns);
+    assert_eq!(entry.remote_ip, "203.0.113.9");
+    assert_eq!(entry.answers, vec!["203.0.113.9", "2001:db8::9"]);
+    assert_eq!(entry.dns_ttl, Some(60));
+    // Sem resposta vista continua "via DNS: RESOLVEDOR", agora com o erro se houver
+    let nx = LogLine::parse("[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → typo.example (via DNS: 192.168.1.1) | fonte=DNS | rcode=NXDOMAIN").unwrap();
+    assert_eq!((nx.remote_ip.as_str(), nx.dns_rcode.as_deref()), ("192.168.1.1", Some("NXDOMAIN")));
+    assert!(nx.answers.is_empty());
+}
@@ -0,0 +1,93 @@
+use std::net::IpAddr;
+
+use traffic_utils::names::{announcements, NameTable};
+use traffic_utils::packet::{Packet, PROTO_UDP};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn name(out: &mut Vec<u8>, n: &str) {
+    for label in n.split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+fn record(out: &mut Vec<u8>, owner: &str, rtype: u16, rdata: &[u8]) {
+    name(out, owner);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&[0x80, 0x01, 0, 0, 0x11, 0x94]); // classe IN com cache-flush, TTL
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+}
+
+// Anúncio típico de Chromecast: PTR do serviço na resposta, A no additional
+fn mdns_announcement() -> Packet {
+    let mut msg = vec![0, 0, 0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 1];
+    let mut target = Vec::new();
+    name(&mut target, "Sala-TV._googlecast._tcp.local");
+    record(&mut msg, "_googlecast._tcp.local", 12, &target);
+    record(&mut msg, "Sala-TV.local", 1, &[192, 168, 1, 30]);
+    Packet::new(1.0, ip("192.168.1.30"), ip("224.0.0.251"), PROTO_UDP).with_transport(5353, 5353, &msg)
+}
+
+fn netbios(n: &str, suffix: u8) -> String {
+    let mut raw = format!("{:<15}", n).into_bytes();
+    raw.push(suffix);
+    raw.iter().flat_map(|b| [b'A' + (b >> 4), b'A' + (b & 0x0f)]).map(char::from).collect()
+}
+
+// Registro de nome (broadcast) com o IP no additional
+fn nbns_registration(n: &str, suffix: u8, nb_flags: u8) -> Packet {
+    let mut msg = vec![0x12, 0x34, 0x29, 0x10, 0, 1, 0, 0, 0, 0, 0, 1];
+    name(&mut msg, &netbios(n, suffix));
+    msg.extend_from_slice(&[0, 0x20, 0, 1]);
+    msg.extend_from_slice(&[0xc0, 12, 0, 0x20, 0, 1, 0, 0x04, 0x93, 0xe0, 0, 6, nb_flags, 0, 192, 168, 1, 40]);
+    Packet::new(2.0, ip("192.168.1.40"), ip("192.168.1.255"), PROTO_UDP).with_transport(137, 137, &msg)
+}
+
+fn llmnr_response() -> Packet {
+    let mut msg = vec![0xab, 0xcd, 0x80, 0x00, 0, 1, 0, 1, 0, 0, 0, 0];
+    name(&mut msg, "notebook");
+    msg.extend_from_slice(&[0, 1, 0, 1]);
+    record(&mut msg, "notebook", 1, &[192, 168, 1, 41]);
+    Packet::new(3.0, ip("192.168.1.41"), ip("192.168.1.10"), PROTO_UDP).with_transport(5355, 51000, &msg)
+}
+
+#[test]
+fn mdns_announcement_gives_name_and_service() {
+    let found = announcements(&mdns_announcement());
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].ip, ip("192.168.1.30"));
+    assert_eq!(found[0].name.as_deref(), Some("Sala-TV"));
+    assert_eq!(found[0].services, vec!["_googlecast._tcp".to_string()]);
+}
+
+#[test]
+fn nbns_registration_and_llmnr_response() {
+    let found = announcements(&nbns_registration("DESKTOP-01", 0x00, 0x00));
+    assert_eq!((found[0].ip, found[0].name.as_deref(), found[0].source), (ip("192.168.1.40"), Some("DESKTOP-01"), "NBNS"));
+    // Nome de grupo (WORKGROUP) e sufixo de domínio não identificam a máquina
+    assert!(announcements(&nbns_registration("WORKGROUP", 0x00, 0x80)).is_empty());
+    assert!(announcements(&nbns_registration("CORP", 0x1c, 0x00)).is_empty());
+
+    let found = announcements(&llmnr_response());
+    assert_eq!((found[0].ip, found[0].name.as_deref()), (ip("192.168.1.41"), Some("notebook")));
+}
+
+#[test]
+fn mdns_name_is_not_replaced_by_netbios() {
+    let mut table = NameTable::new();
+    let mut pkt = mdns_announcement();
+    *pkt.payload.last_mut().unwrap() = 40; // mesmo host do NBNS
+    pkt.src = ip("192.168.1.40");
+    assert!(table.feed(&pkt));
+    assert!(!table.feed(&nbns_registration("DESKTOP-01", 0x00, 0x00)));
+
+    let host = &table.hosts["192.168.1.40"];
+    assert_eq!((host.name.as_deref(), host.source.as_deref()), (Some("Sala-TV"), Some("mDNS")));
+    assert!(host.services.contains("_googlecast._tcp"));
+    assert_eq!(table.names()["192.168.1.40"], "Sala-TV");
+}
@@ -0,0 +1,45 @@
+use traffic_utils::devices::DeviceTable;
+use traffic_utils::oui::{is_randomized, OuiDb};
+use traffic_utils::packet::decode_ethernet;
+
+fn db() -> OuiDb {
+    let mut db = OuiDb::default();
+    db.insert_line("3C-22-FB   (hex)\t\tApple, Inc.");
+    db.insert_line("3C22FB     (base 16)\t\tApple, Inc.");
+    db.insert_line("MA-L,8C79F5,\"Samsung Electronics Co.,Ltd\",\"Suwon\"");
+    db.insert_line("00:1A:11\tGoogle\tGoogle, Inc.");
+    db.insert_line("00:1B:C5:00:00:00/36\tConverg\tConverging Systems Inc.");
+    db.insert_line("# comentário");
+    db
+}
+
+#[test]
+fn parses_ieee_and_wireshark_formats() {
+    let db = db();
+    assert_eq!(db.len(), 3);
+    assert_eq!(db.vendor("3c:22:fb:10:20:30"), Some("Apple, Inc."));
+    assert_eq!(db.vendor("8C:79:F5:00:11:22"), Some("Samsung Electronics Co.,Ltd"));
+    assert_eq!(db.vendor("00:1a:11:aa:bb:cc"), Some("Google, Inc."));
+    assert_eq!(db.vendor("00:1b:c5:00:00:01"), None);
+}
+
+#[test]
+fn locally_administered_macs_are_randomized() {
+    let db = db();
+    assert!(is_randomized("da:a1:19:00:00:01"));
+    assert!(!is_randomized("3c:22:fb:10:20:30"));
+    assert_eq!(db.label("3e:22:fb:10:20:30").as_deref(), Some("randomized"));
+    assert_eq!(db.label("00:00:5e:00:00:01"), None);
+}
+
+#[test]
+fn device_table_stores_vendor_with_binding() {
+    let mut frame = vec![0xff; 6];
+    frame.extend_from_slice(&[0x3c, 0x22, 0xfb, 1, 2, 3, 0x08, 0x06, 0, 1, 0x08, 0x00, 6, 4, 0, 1]);
+    frame.extend_from_slice(&[0x3c, 0x22, 0xfb, 1, 2, 3, 192, 168, 1, 50, 0, 0, 0, 0, 0, 0, 192, 168, 1, 1]);
+
+    let mut table = DeviceTable::new();
+    table.set_oui(db());
+    table.feed(&decode_ethernet(1.0, &frame).unwrap());
+    assert_eq!(table.vendor_of("192.168.1.50"), Some("Apple, Inc."));
+}
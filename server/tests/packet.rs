@@ -0,0 +1,73 @@
+use std::net::IpAddr;
+
+use traffic_utils::packet::{decode_ethernet, decode_raw_ip, PROTO_TCP, PROTO_UDP};
+
+fn ethernet(ethertype: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn ipv4_udp(payload: &[u8]) -> Vec<u8> {
+    let total = 20 + 8 + payload.len();
+    let mut p = vec![0x45, 0, (total >> 8) as u8, total as u8, 0, 0, 0, 0, 64, 17, 0, 0, 192, 168, 1, 10, 8, 8, 8, 8];
+    p.extend_from_slice(&[0x13, 0x88, 0, 53, 0, (8 + payload.len()) as u8, 0, 0]);
+    p.extend_from_slice(payload);
+    p
+}
+
+fn ipv6(next: u8, ext_and_l4: &[u8]) -> Vec<u8> {
+    let mut p = vec![0x60, 0, 0, 0];
+    p.extend_from_slice(&(ext_and_l4.len() as u16).to_be_bytes());
+    p.extend_from_slice(&[next, 64]);
+    p.extend_from_slice(&"fd00::10".parse::<std::net::Ipv6Addr>().unwrap().octets());
+    p.extend_from_slice(&"2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+    p.extend_from_slice(ext_and_l4);
+    p
+}
+
+fn tcp(payload: &[u8]) -> Vec<u8> {
+    let mut t = vec![0xc3, 0x50, 0x01, 0xbb, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0];
+    t.extend_from_slice(payload);
+    t
+}
+
+#[test]
+fn decodes_ipv4_udp_with_ethernet_padding() {
+    let mut frame = ethernet(0x0800, &ipv4_udp(b"abc"));
+    frame.extend_from_slice(&[0; 20]); // padding até o mínimo do Ethernet
+    let pkt = decode_ethernet(1.0, &frame).unwrap();
+
+    assert_eq!(pkt.src, "192.168.1.10".parse::<IpAddr>().unwrap());
+    assert_eq!((pkt.protocol, pkt.src_port, pkt.dst_port), (PROTO_UDP, 5000, 53));
+    assert_eq!(pkt.payload, b"abc");
+    assert_eq!(pkt.len, 3);
+}
+
+#[test]
+fn decodes_vlan_tagged_ipv6_tcp() {
+    let mut tagged = vec![0x00, 0x0a, 0x86, 0xdd]; // VLAN 10 -> IPv6
+    tagged.extend(ipv6(6, &tcp(b"hello")));
+    let pkt = decode_ethernet(2.0, &ethernet(0x8100, &tagged)).unwrap();
+
+    assert_eq!(pkt.src, "fd00::10".parse::<IpAddr>().unwrap());
+    assert_eq!(pkt.dst, "2001:db8::1".parse::<IpAddr>().unwrap());
+    assert_eq!((pkt.protocol, pkt.src_port, pkt.dst_port), (PROTO_TCP, 50000, 443));
+    assert_eq!((pkt.seq, pkt.tcp_flags), (1, 0x18));
+    assert_eq!(pkt.payload, b"hello");
+    assert_eq!(pkt.len, 5);
+}
+
+#[test]
+fn skips_ipv6_extension_headers() {
+    // hop-by-hop (8 bytes) antes do TCP
+    let mut ext = vec![6, 0, 0, 0, 0, 0, 0, 0];
+    ext.extend(tcp(b"x"));
+    let pkt = decode_raw_ip(3.0, &ipv6(0, &ext)).unwrap();
+    assert_eq!((pkt.protocol, pkt.dst_port, pkt.len), (PROTO_TCP, 443, 1));
+
+    // fragmento que não é o primeiro: sem cabeçalho L4
+    let frag = [6, 0, 0x05, 0x00, 0, 0, 0, 1, 1, 2, 3, 4];
+    assert!(decode_raw_ip(3.0, &ipv6(44, &frag)).is_none());
+}
@@ -0,0 +1,275 @@
+use std::io::Cursor;
+use std::net::IpAddr;
+
+use traffic_utils::capture::{PacketSource, SyntheticSource, TsharkLines};
+use traffic_utils::counters::{history_path, ByteCounter, Rollover};
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{Packet, PROTO_TCP, PROTO_UDP};
+use traffic_utils::pipeline::{DomainEvent, DomainPipeline};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn lan() -> LanMatcher {
+    LanMatcher::new(parse_cidr_list("192.168.1.0/24,fd00::/8").unwrap(), Vec::new())
+}
+
+fn dns_query(name: &str) -> Vec<u8> {
+    let mut msg = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.extend_from_slice(&[0, 0, 1, 0, 1]);
+    msg
+}
+
+fn dns_response_a(name: &str, addr: [u8; 4]) -> Vec<u8> {
+    let mut msg = dns_query(name);
+    msg[2] = 0x81;
+    msg[3] = 0x80;
+    msg[7] = 1;
+    msg.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+    msg.extend_from_slice(&addr);
+    msg
+}
+
+fn http_get(host: &str) -> Vec<u8> {
+    format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host).into_bytes()
+}
+
+fn run(source: &mut dyn PacketSource, pipeline: &mut DomainPipeline) -> Vec<DomainEvent> {
+    let mut events = Vec::new();
+    while let Some(pkt) = source.next_packet().unwrap() {
+        events.extend(pipeline.process(&pkt));
+    }
+    events.extend(pipeline.flush());
+    events
+}
+
+#[test]
+fn dns_confirmed_by_http_is_not_logged_twice() {
+    let packets = vec![
+        Packet::new(100.0, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5000, 53, &dns_query("example.com")),
+        Packet::new(101.0, ip("192.168.1.10"), ip("93.184.216.34"), PROTO_TCP).with_transport(40000, 80, &http_get("example.com")),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].source, "HTTP");
+    assert_eq!(events[0].remote, "93.184.216.34");
+    assert!(!events[0].delayed);
+}
+
+#[test]
+fn unconfirmed_dns_is_logged_as_delayed() {
+    let packets = vec![
+        Packet::new(100.0, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5000, 53, &dns_query("b.example")),
+        Packet::new(100.5, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5001, 53, &dns_query("a.example")),
+        Packet::new(110.0, ip("192.168.1.11"), ip("1.1.1.1"), PROTO_TCP).with_transport(40000, 80, &http_get("late.example")),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    let domains: Vec<_> = events.iter().map(|e| (e.domain.as_str(), e.delayed)).collect();
+    assert_eq!(domains, vec![("b.example", true), ("a.example", true), ("late.example", false)]);
+    assert_eq!(events[0].remote, "192.168.1.1");
+}
+
+#[test]
+fn delayed_dns_is_logged_with_resolved_address() {
+    let packets = vec![
+        Packet::new(100.0, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5000, 53, &dns_query("cdn.example")),
+        Packet::new(100.1, ip("192.168.1.1"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 5000, &dns_response_a("cdn.example", [203, 0, 113, 9])),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].client, "192.168.1.10");
+    assert_eq!(events[0].remote, "203.0.113.9");
+    assert_eq!(events[0].answers, vec![ip("203.0.113.9")]);
+    assert_eq!((events[0].dns_ttl, events[0].dns_rcode.as_deref()), (Some(60), None));
+    assert!(events[0].delayed);
+}
+
+#[test]
+fn answers_from_any_resolver_are_matched_by_id() {
+    let mut other_id = dns_response_a("dot.example", [198, 51, 100, 7]);
+    other_id[1] = 0x99;
+    let packets = vec![
+        Packet::new(100.0, ip("192.168.1.10"), ip("8.8.8.8"), PROTO_UDP).with_transport(5000, 53, &dns_query("cdn.example")),
+        Packet::new(100.1, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 5000, &dns_response_a("cdn.example", [203, 0, 113, 9])),
+        // Resposta com outro id não completa a query pendente
+        Packet::new(100.2, ip("192.168.1.11"), ip("192.168.1.2"), PROTO_UDP).with_transport(5001, 53, &dns_query("dot.example")),
+        Packet::new(100.3, ip("192.168.1.2"), ip("192.168.1.11"), PROTO_UDP).with_transport(53, 5001, &other_id),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    let remotes: Vec<_> = events.iter().map(|e| (e.domain.as_str(), e.remote.as_str(), e.delayed)).collect();
+    assert_eq!(remotes, vec![("cdn.example", "203.0.113.9", true), ("dot.example", "192.168.1.2", true)]);
+}
+
+#[test]
+fn nxdomain_keeps_the_resolver_and_records_the_rcode() {
+    let mut nxdomain = dns_query("typo.example");
+    nxdomain[2] = 0x81;
+    nxdomain[3] = 0x83;
+    let packets = vec![
+        Packet::new(100.0, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5000, 53, &dns_query("typo.example")),
+        Packet::new(100.1, ip("192.168.1.1"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 5000, &nxdomain),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].remote, "192.168.1.1");
+    assert!(events[0].answers.is_empty());
+    assert_eq!((events[0].dns_ttl, events[0].dns_rcode.as_deref()), (None, Some("NXDOMAIN")));
+}
+
+#[test]
+fn repeated_events_within_a_second_are_dropped() {
+    let lines = "\
+200.0\t192.168.1.20\t8.8.4.4\t\tgoogle.com\t
+200.5\t192.168.1.20\t8.8.4.4\t\tgoogle.com\t
+201.6\t192.168.1.20\t8.8.4.4\t\tgoogle.com\t
+201.7\t10.0.0.1\t8.8.4.4\t\tgoogle.com\t
+";
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut TsharkLines::new(Cursor::new(lines)), &mut pipeline);
+
+    let ts: Vec<_> = events.iter().map(|e| e.ts).collect();
+    assert_eq!(ts, vec![200.0, 201.6]);
+    assert!(events.iter().all(|e| e.source == "TLS" && e.client == "192.168.1.20"));
+}
+
+#[test]
+fn ignored_domains_and_clients_are_skipped() {
+    let packets = vec![
+        Packet::new(1.0, ip("192.168.1.10"), ip("1.1.1.1"), PROTO_TCP).with_transport(1, 80, &http_get("ads.example")),
+        Packet::new(2.0, ip("192.168.1.99"), ip("1.1.1.1"), PROTO_TCP).with_transport(1, 80, &http_get("ok.example")),
+        Packet::new(3.0, ip("192.168.1.10"), ip("1.1.1.1"), PROTO_TCP).with_transport(1, 80, &http_get("ok.example")),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    pipeline.ignore_domains.insert("ads.example".to_string());
+    pipeline.ignore_clients.insert("192.168.1.99".to_string());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].client.as_str(), events[0].domain.as_str()), ("192.168.1.10", "ok.example"));
+}
+
+#[test]
+fn byte_counter_splits_in_and_out() {
+    let mut counter = ByteCounter::new(lan());
+    counter.process(&Packet::new(1.0, ip("192.168.1.10"), ip("8.8.8.8"), PROTO_UDP).with_transport(1, 53, &[0; 40]));
+    counter.process(&Packet::new(2.0, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 1, &[0; 100]));
+    counter.process(&Packet::new(3.0, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_TCP).with_transport(443, 1, &[]));
+
+    let c = &counter.stats["192.168.1.10"];
+    assert_eq!((c.bytes_out, c.bytes_in, c.bytes_total), (40, 100, 140));
+    // o ACK vazio conta como pacote e atividade, mas não soma bytes
+    assert_eq!((c.packets_out, c.packets_in), (1, 2));
+    assert_eq!((c.last_seen_out, c.last_seen_any), (1.0, 3.0));
+    assert!(!counter.stats.contains_key("8.8.8.8"));
+}
+
+#[test]
+fn ipv6_clients_are_classified_by_prefix() {
+    let packets = vec![
+        Packet::new(1.0, ip("fd12:3456::10"), ip("2606:4700::1111"), PROTO_TCP).with_transport(40000, 80, &http_get("v6.example")),
+        Packet::new(2.0, ip("2606:4700::1111"), ip("2a00:1450::1"), PROTO_TCP).with_transport(40000, 80, &http_get("transit.example")),
+    ];
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].client.as_str(), events[0].remote.as_str()), ("fd12:3456::10", "2606:4700::1111"));
+
+    let mut counter = ByteCounter::new(lan());
+    counter.process(&Packet::new(1.0, ip("2606:4700::1111"), ip("fd12:3456::10"), PROTO_UDP).with_transport(443, 5000, &[0; 1200]));
+    assert_eq!(counter.stats["fd12:3456::10"].bytes_in, 1200);
+}
+
+#[test]
+fn events_and_counters_are_tagged_with_interface() {
+    let packets = vec![
+        Packet::new(1.0, ip("192.168.1.10"), ip("192.168.1.1"), PROTO_UDP).with_transport(5000, 53, &dns_query("a.example")).on_iface("br-lan"),
+        Packet::new(1.5, ip("192.168.1.50"), ip("1.1.1.1"), PROTO_TCP).with_transport(40000, 80, &http_get("guest.example")).on_iface("br-guest"),
+        // mesmo pacote roteado aparece nas duas bridges: vira uma linha só
+        Packet::new(2.0, ip("192.168.1.10"), ip("192.168.1.50"), PROTO_TCP).with_transport(40001, 80, &http_get("nas.lan")).on_iface("br-lan"),
+        Packet::new(2.0, ip("192.168.1.10"), ip("192.168.1.50"), PROTO_TCP).with_transport(40001, 80, &http_get("nas.lan")).on_iface("br-guest"),
+    ];
+    let mut counter = ByteCounter::new(lan());
+    counter.segments.insert("br-lan", parse_cidr_list("192.168.1.0/28").unwrap());
+    counter.segments.insert("br-guest", parse_cidr_list("192.168.1.48/28").unwrap());
+    for pkt in &packets {
+        counter.process(pkt);
+    }
+    let mut pipeline = DomainPipeline::new(lan());
+    let events = run(&mut SyntheticSource::new(packets), &mut pipeline);
+
+    let tagged: Vec<_> = events.iter().map(|e| (e.domain.as_str(), e.iface.as_deref())).collect();
+    assert_eq!(tagged, vec![("guest.example", Some("br-guest")), ("nas.lan", Some("br-lan")), ("a.example", Some("br-lan"))]);
+
+    let guest = &counter.stats["192.168.1.50"];
+    assert_eq!((guest.iface.as_deref(), guest.bytes_in), (Some("br-guest"), http_get("nas.lan").len() as u64));
+    assert_eq!(counter.stats["192.168.1.10"].iface.as_deref(), Some("br-lan"));
+}
+
+#[test]
+fn byte_counter_splits_by_protocol_and_service_port() {
+    let mut counter = ByteCounter::new(lan());
+    counter.service_ports.insert(8443);
+    counter.process(&Packet::new(1.0, ip("192.168.1.10"), ip("8.8.8.8"), PROTO_UDP).with_transport(40000, 53, &[0; 40]));
+    counter.process(&Packet::new(1.1, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 40000, &[0; 100]));
+    counter.process(&Packet::new(2.0, ip("1.2.3.4"), ip("192.168.1.10"), PROTO_TCP).with_transport(8443, 50000, &[0; 1000]));
+    counter.process(&Packet::new(3.0, ip("192.168.1.10"), ip("1.2.3.4"), PROTO_TCP).with_transport(50000, 9999, &[0; 10]));
+
+    let c = &counter.stats["192.168.1.10"].breakdown;
+    assert_eq!((c.protocols["udp"].bytes_out, c.protocols["udp"].bytes_in), (40, 100));
+    assert_eq!(c.protocols["tcp"].bytes_total, 1010);
+    assert_eq!(c.ports["53"].bytes_total, 140);
+    assert_eq!(c.ports["8443"].bytes_in, 1000);
+    assert_eq!(c.ports["other"].bytes_out, 10);
+    assert_eq!(counter.network, counter.stats["192.168.1.10"].breakdown);
+}
+
+#[test]
+fn byte_counter_tracks_packets_and_window_rates() {
+    let mut counter = ByteCounter::new(lan());
+    // 1000 bytes/s de download durante 60s, e um upload só no último segundo
+    for s in 0..60 {
+        counter.process(&Packet::new(100.0 + s as f64, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_TCP).with_transport(443, 1, &[0; 1000]));
+    }
+    counter.process(&Packet::new(159.5, ip("192.168.1.10"), ip("8.8.8.8"), PROTO_TCP).with_transport(1, 443, &[0; 500]));
+
+    let c = &counter.stats["192.168.1.10"];
+    assert_eq!((c.packets_in, c.packets_out), (60, 1));
+    assert_eq!(c.rates.bps(159.9, 10), (8000.0, 400.0));
+    assert_eq!(c.rates.bps(159.9, 60), (8000.0, 500.0 * 8.0 / 60.0));
+    assert_eq!(c.rates.bps(159.9, 300), (60_000.0 * 8.0 / 300.0, 500.0 * 8.0 / 300.0));
+    // Sem tráfego novo, a janela curta esvazia
+    assert_eq!(c.rates.bps(200.0, 10), (0.0, 0.0));
+}
+
+#[test]
+fn rollover_periods_history_file_and_reset() {
+    let ts = 1_764_590_400.0; // 2025-12-01 12:00 UTC: mesmo dia em qualquer fuso até ±11h
+    let day = Rollover::Daily.period_of(ts);
+    assert_eq!(day, "2025-12-01");
+    assert_eq!(Rollover::Monthly.period_of(ts), "2025-12");
+    assert_eq!(Rollover::Never.period_of(ts), "");
+    assert_eq!(history_path("/var/log/traffic-bytes.json", &day), "/var/log/traffic-bytes-2025-12-01.json");
+
+    let mut counter = ByteCounter::new(lan());
+    counter.process(&Packet::new(ts, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 5000, &[0; 100]));
+    counter.reset();
+    assert!(counter.stats.is_empty() && counter.network.protocols.is_empty());
+    counter.process(&Packet::new(ts + 1.0, ip("8.8.8.8"), ip("192.168.1.10"), PROTO_UDP).with_transport(53, 5000, &[0; 100]));
+    assert_eq!(counter.stats["192.168.1.10"].packets_in, 1);
+}
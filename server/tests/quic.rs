@@ -0,0 +1,142 @@
+use std::net::IpAddr;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::Aes128Gcm;
+use traffic_utils::lan::{parse_cidr_list, LanMatcher};
+use traffic_utils::packet::{Packet, PROTO_UDP};
+use traffic_utils::quic::{self, QuicTracker};
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+// Mensagem de handshake ClientHello (sem cabeçalho de registro, como no QUIC)
+fn client_hello(sni: &str, padding: usize) -> Vec<u8> {
+    let mut sni_ext = vec![0, 0];
+    sni_ext.extend_from_slice(&((sni.len() + 5) as u16).to_be_bytes());
+    sni_ext.extend_from_slice(&((sni.len() + 3) as u16).to_be_bytes());
+    sni_ext.push(0);
+    sni_ext.extend_from_slice(&(sni.len() as u16).to_be_bytes());
+    sni_ext.extend_from_slice(sni.as_bytes());
+    let alpn_ext = [0x00, 0x10, 0, 5, 0, 3, 2, b'h', b'3'];
+    let mut pad_ext = vec![0x00, 0x15];
+    pad_ext.extend_from_slice(&(padding as u16).to_be_bytes());
+    pad_ext.extend(vec![0; padding]);
+
+    let exts: Vec<u8> = [sni_ext, alpn_ext.to_vec(), pad_ext].concat();
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&[1; 32]);
+    body.extend_from_slice(&[0, 0, 2, 0x13, 0x01, 1, 0]);
+    body.extend_from_slice(&(exts.len() as u16).to_be_bytes());
+    body.extend(exts);
+    let mut hs = vec![0x01, 0, (body.len() >> 8) as u8, body.len() as u8];
+    hs.extend(body);
+    hs
+}
+
+fn crypto_frame(offset: u16, data: &[u8]) -> Vec<u8> {
+    // varints de 2 bytes (prefixo 01)
+    let mut f = vec![0x06];
+    f.extend_from_slice(&(0x4000 | offset).to_be_bytes());
+    f.extend_from_slice(&(0x4000 | data.len() as u16).to_be_bytes());
+    f.extend_from_slice(data);
+    f
+}
+
+// Monta e protege um Initial v1 como um cliente faria
+fn protect_initial(dcid: &[u8], pn: u32, frames: &[u8]) -> Vec<u8> {
+    let keys = quic::client_initial_keys(1, dcid).unwrap();
+    let mut plain = frames.to_vec();
+    plain.resize(1100, 0); // PADDING
+    let length = 4 + plain.len() + 16;
+
+    let mut header = vec![0xc3, 0, 0, 0, 1, dcid.len() as u8];
+    header.extend_from_slice(dcid);
+    header.extend_from_slice(&[0, 0]); // scid vazio, token vazio
+    header.extend_from_slice(&(0x4000 | length as u16).to_be_bytes());
+    let pn_offset = header.len();
+    header.extend_from_slice(&pn.to_be_bytes());
+
+    let mut nonce = keys.iv.clone();
+    for (i, b) in (pn as u64).to_be_bytes().iter().enumerate() {
+        nonce[4 + i] ^= b;
+    }
+    let cipher = Aes128Gcm::new_from_slice(&keys.key).unwrap();
+    let sealed = cipher.encrypt(aes_gcm::Nonce::from_slice(&nonce), Payload { msg: &plain, aad: &header }).unwrap();
+
+    let mut packet = header;
+    packet.extend(sealed);
+    let hp = Aes128::new_from_slice(&keys.hp).unwrap();
+    let mut mask = aes::Block::clone_from_slice(&packet[pn_offset + 4..pn_offset + 20]);
+    hp.encrypt_block(&mut mask);
+    packet[0] ^= mask[0] & 0x0f;
+    for i in 0..4 {
+        packet[pn_offset + i] ^= mask[1 + i];
+    }
+    packet
+}
+
+fn datagram(ts: f64, payload: &[u8]) -> Packet {
+    let src: IpAddr = "192.168.1.30".parse().unwrap();
+    let dst: IpAddr = "142.250.78.14".parse().unwrap();
+    Packet::new(ts, src, dst, PROTO_UDP).with_transport(55555, 443, payload)
+}
+
+#[test]
+fn initial_keys_match_rfc9001_vectors() {
+    let keys = quic::client_initial_keys(1, &hex("8394c8f03e515708")).unwrap();
+    assert_eq!(keys.key, hex("1f369613dd76d5467730efcbe3b1a22d"));
+    assert_eq!(keys.iv, hex("fa044b2f42a3fd3b46fb255c"));
+    assert_eq!(keys.hp, hex("9f50449e04a0e810283a1e9933adedd2"));
+}
+
+#[test]
+fn extracts_sni_from_single_initial() {
+    let dcid = hex("8394c8f03e515708");
+    let packet = protect_initial(&dcid, 2, &crypto_frame(0, &client_hello("www.youtube.com", 0)));
+
+    let initial = quic::decrypt_initial(&packet).unwrap();
+    assert_eq!(initial.dcid, dcid);
+
+    let hello = QuicTracker::new().feed(&datagram(1.0, &packet)).unwrap();
+    assert_eq!(hello.sni.as_deref(), Some("www.youtube.com"));
+    assert_eq!(hello.alpn, vec!["h3"]);
+}
+
+#[test]
+fn reassembles_crypto_across_initials_out_of_order() {
+    let dcid = hex("0011223344556677");
+    let ch = client_hello("rr1.googlevideo.com", 1500);
+    let (a, b) = ch.split_at(900);
+    let first = protect_initial(&dcid, 0, &crypto_frame(900, b));
+    let second = protect_initial(&dcid, 1, &crypto_frame(0, a));
+
+    let mut tracker = QuicTracker::new();
+    assert!(tracker.feed(&datagram(1.0, &first)).is_none());
+    let hello = tracker.feed(&datagram(1.01, &second)).unwrap();
+    assert_eq!(hello.sni.as_deref(), Some("rr1.googlevideo.com"));
+}
+
+#[test]
+fn tampered_initial_is_rejected() {
+    let mut packet = protect_initial(&hex("8394c8f03e515708"), 2, &crypto_frame(0, &client_hello("a.example", 0)));
+    let last = packet.len() - 1;
+    packet[last] ^= 0xff;
+    assert!(quic::decrypt_initial(&packet).is_none());
+}
+
+#[test]
+fn pipeline_logs_quic_source() {
+    let packet = protect_initial(&hex("8394c8f03e515708"), 0, &crypto_frame(0, &client_hello("www.instagram.com", 0)));
+    let lan = LanMatcher::new(parse_cidr_list("192.168.1.0/24").unwrap(), Vec::new());
+    let mut pipeline = traffic_utils::pipeline::DomainPipeline::new(lan);
+    let events = pipeline.process(&datagram(5.0, &packet));
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].source, "QUIC");
+    assert_eq!(events[0].domain, "www.instagram.com");
+    assert_eq!(events[0].remote, "142.250.78.14");
+    assert_eq!(events[0].alpn.as_deref(), Some("h3"));
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use traffic_utils::rotation::{log_exists, read_log, rotated_days, rotated_path, RotatingFile, RotationConfig};
+
+const NOON: f64 = 1_764_590_400.0; // 2025-12-01 12:00 UTC
+const DAY: f64 = 86400.0;
+
+fn date(ts: f64) -> String {
+    let dt: DateTime<Local> = DateTime::from(DateTime::from_timestamp(ts as i64, 0).unwrap());
+    dt.format("%Y-%m-%d").to_string()
+}
+
+fn temp_log(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("traffic-domains.log").to_str().unwrap().to_string()
+}
+
+fn cleanup(base: &str) {
+    let _ = fs::remove_dir_all(Path::new(base).parent().unwrap());
+}
+
+#[test]
+fn rotates_at_local_midnight_into_dated_file() {
+    let base = temp_log("rotate-plain");
+    let mut log = RotatingFile::new(&base, RotationConfig::default());
+    log.append(NOON, "day one");
+    log.append(NOON + DAY, "day two");
+    // Evento atrasado de antes da meia-noite fica no arquivo novo, não roda de volta
+    log.append(NOON + 1.0, "late");
+
+    assert_eq!(fs::read_to_string(rotated_path(&base, &date(NOON))).unwrap(), "day one\n");
+    assert_eq!(fs::read_to_string(&base).unwrap(), "day two\nlate\n");
+    // Arquivo que já existia: o dia vem do mtime e roda na primeira linha de outro dia
+    let mut reopened = RotatingFile::new(&base, RotationConfig { keep_days: None, ..Default::default() });
+    reopened.append(4_000_000_000.0, "much later");
+    assert!(log_exists(&rotated_path(&base, &date(Local::now().timestamp() as f64))));
+    assert_eq!(fs::read_to_string(&base).unwrap(), "much later\n");
+    cleanup(&base);
+}
+
+#[test]
+fn rolls_on_clock_without_new_lines() {
+    let base = temp_log("rotate-clock");
+    let mut log = RotatingFile::new(&base, RotationConfig::default());
+    log.append(NOON, "day one");
+    // Dia seguinte sem eventos: o relógio do loop já roda o arquivo
+    log.roll(NOON + DAY);
+
+    assert_eq!(fs::read_to_string(rotated_path(&base, &date(NOON))).unwrap(), "day one\n");
+    assert!(!Path::new(&base).exists());
+    log.append(NOON + DAY + 1.0, "day two");
+    assert_eq!(fs::read_to_string(&base).unwrap(), "day two\n");
+    cleanup(&base);
+}
+
+#[test]
+fn gzip_keeps_days_readable() {
+    let base = temp_log("rotate-gzip");
+    let config = RotationConfig { gzip: true, ..Default::default() };
+    // Dia antigo em texto puro (ex: de antes do LOG_GZIP) é comprimido na manutenção
+    fs::write(rotated_path(&base, &date(NOON - DAY)), "legacy\n").unwrap();
+    let mut log = RotatingFile::new(&base, config);
+    log.append(NOON, "first");
+    log.append(NOON + DAY, "second");
+
+    let day_one = rotated_path(&base, &date(NOON));
+    assert!(!day_one.exists() && log_exists(&day_one));
+    assert_eq!(read_log(&day_one).unwrap(), "first\n");
+    assert_eq!(read_log(&rotated_path(&base, &date(NOON - DAY))).unwrap(), "legacy\n");
+    assert!(rotated_days(&base).iter().all(|(_, path, _)| path.extension().is_some_and(|e| e == "gz")));
+    cleanup(&base);
+}
+
+#[test]
+fn retention_by_age_and_total_size() {
+    let base = temp_log("rotate-retention");
+    for (i, content) in ["a".repeat(100), "b".repeat(100), "c".repeat(100), "d".repeat(100)].iter().enumerate() {
+        fs::write(rotated_path(&base, &date(NOON + i as f64 * DAY)), content).unwrap();
+    }
+    let today = date(NOON + 4.0 * DAY);
+    // Sem configuração não se apaga nada
+    RotatingFile::new(&base, RotationConfig::default()).maintain(&today);
+    assert_eq!(rotated_days(&base).len(), 4);
+    RotatingFile::new(&base, RotationConfig { keep_days: Some(3), ..Default::default() }).maintain(&today);
+    let kept: Vec<String> = rotated_days(&base).into_iter().map(|d| d.0).collect();
+    assert_eq!(kept, vec![date(NOON + DAY), date(NOON + 2.0 * DAY), date(NOON + 3.0 * DAY)]);
+
+    RotatingFile::new(&base, RotationConfig { keep_days: None, max_bytes: Some(250), ..Default::default() }).maintain(&today);
+    let kept: Vec<String> = rotated_days(&base).into_iter().map(|d| d.0).collect();
+    assert_eq!(kept, vec![date(NOON + 2.0 * DAY), date(NOON + 3.0 * DAY)]);
+    cleanup(&base);
+}
+
+#[test]
+fn each_dataset_reads_its_own_retention_variables() {
+    std::env::set_var("ROTTEST_RETENTION_DAYS", "7");
+    std::env::set_var("ROTTEST_RETENTION_MB", "2");
+    let flows = RotationConfig::retention_from_env("ROTTEST");
+    assert_eq!((flows.keep_days, flows.max_bytes), (Some(7), Some(2 * 1_048_576)));
+    assert_eq!(RotationConfig::retention_from_env("ROTTEST_UNSET"), RotationConfig::default());
+}
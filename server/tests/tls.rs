@@ -0,0 +1,156 @@
+use std::net::IpAddr;
+
+use traffic_utils::packet::{Packet, PROTO_TCP, TCP_ACK, TCP_SYN};
+use traffic_utils::parse_log_line;
+use traffic_utils::tls::{self, HelloReassembler};
+
+fn ext(ext_type: u16, body: &[u8]) -> Vec<u8> {
+    let mut out = ext_type.to_be_bytes().to_vec();
+    out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+// Registro TLS com um ClientHello; `padding` força o tamanho a passar de um segmento
+fn client_hello(sni: &str, alpn: &[&str], versions: &[u16], padding: usize) -> Vec<u8> {
+    let mut sni_body = ((sni.len() + 3) as u16).to_be_bytes().to_vec();
+    sni_body.push(0);
+    sni_body.extend_from_slice(&(sni.len() as u16).to_be_bytes());
+    sni_body.extend_from_slice(sni.as_bytes());
+
+    let mut alpn_list = Vec::new();
+    for p in alpn {
+        alpn_list.push(p.len() as u8);
+        alpn_list.extend_from_slice(p.as_bytes());
+    }
+    let mut alpn_body = (alpn_list.len() as u16).to_be_bytes().to_vec();
+    alpn_body.extend(alpn_list);
+
+    let mut sv_body = vec![(versions.len() * 2) as u8];
+    for v in versions {
+        sv_body.extend_from_slice(&v.to_be_bytes());
+    }
+
+    let mut exts = ext(0x0000, &sni_body);
+    exts.extend(ext(0x0010, &alpn_body));
+    exts.extend(ext(0x002b, &sv_body));
+    exts.extend(ext(0x0015, &vec![0; padding]));
+
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&[7; 32]);
+    body.push(0); // session id
+    body.extend_from_slice(&[0, 2, 0x13, 0x01]); // cipher suites
+    body.extend_from_slice(&[1, 0]); // compressão
+    body.extend_from_slice(&(exts.len() as u16).to_be_bytes());
+    body.extend(exts);
+
+    let mut hs = vec![0x01, 0, (body.len() >> 8) as u8, body.len() as u8];
+    hs.extend(body);
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(hs.len() as u16).to_be_bytes());
+    record.extend(hs);
+    record
+}
+
+const ISN: u32 = 4_294_966_000; // perto do fim: o fluxo dá a volta no u32 no meio do hello
+
+fn segment(ts: f64, payload: &[u8]) -> Packet {
+    segment_at(ts, ISN.wrapping_add(1), payload)
+}
+
+// Segmento com `offset` bytes depois do SYN
+fn segment_at(ts: f64, offset: u32, payload: &[u8]) -> Packet {
+    let src: IpAddr = "192.168.1.10".parse().unwrap();
+    let dst: IpAddr = "142.250.0.1".parse().unwrap();
+    Packet::new(ts, src, dst, PROTO_TCP).with_transport(50000, 443, payload).with_flags(TCP_ACK).with_seq(ISN.wrapping_add(1).wrapping_add(offset))
+}
+
+fn syn(ts: f64) -> Packet {
+    let src: IpAddr = "192.168.1.10".parse().unwrap();
+    let dst: IpAddr = "142.250.0.1".parse().unwrap();
+    Packet::new(ts, src, dst, PROTO_TCP).with_transport(50000, 443, &[]).with_flags(TCP_SYN).with_seq(ISN)
+}
+
+#[test]
+fn parses_sni_alpn_and_version() {
+    let record = client_hello("www.youtube.com", &["h2", "http/1.1"], &[0x1a1a, 0x0304, 0x0303], 0);
+    let hello = tls::parse_client_hello(&record[5..]).unwrap();
+
+    assert_eq!(hello.sni.as_deref(), Some("www.youtube.com"));
+    assert_eq!(hello.alpn, vec!["h2", "http/1.1"]);
+    assert_eq!(hello.legacy_version, 0x0303);
+    assert_eq!(hello.version_name(), "1.3");
+    assert_eq!(tls::client_hello_sni(&record).as_deref(), Some("www.youtube.com"));
+}
+
+#[test]
+fn reassembles_hello_split_across_segments() {
+    let record = client_hello("big.example", &["http/1.1"], &[], 3000);
+    assert!(tls::client_hello_sni(&record[..1400]).is_none());
+
+    let mut asm = HelloReassembler::new();
+    assert!(asm.feed(&segment_at(1.0, 0, &record[..1400])).is_none());
+    assert!(asm.feed(&segment_at(1.1, 1400, &record[1400..2800])).is_none());
+    let hello = asm.feed(&segment_at(1.2, 2800, &record[2800..])).unwrap();
+
+    assert_eq!(hello.sni.as_deref(), Some("big.example"));
+    assert_eq!(hello.alpn, vec!["http/1.1"]);
+    assert_eq!(hello.version_name(), "1.2");
+}
+
+#[test]
+fn reassembles_by_sequence_number_despite_reordering_and_retransmission() {
+    let record = client_hello("reordered.example", &["h2"], &[], 3000);
+    let mut asm = HelloReassembler::new();
+    assert!(asm.feed(&segment_at(1.0, 0, &record[..1400])).is_none());
+    assert!(asm.feed(&segment_at(1.1, 2800, &record[2800..])).is_none());
+    // Primeiro segmento retransmitido, agora com mais bytes (sobrepõe o segundo)
+    assert!(asm.feed(&segment_at(1.2, 0, &record[..2000])).is_none());
+    let hello = asm.feed(&segment_at(1.3, 1400, &record[1400..2800])).unwrap();
+    assert_eq!(hello.sni.as_deref(), Some("reordered.example"));
+    assert_eq!(hello.alpn, vec!["h2"]);
+
+    // Com o SYN visto, o segundo segmento chegando antes do primeiro também serve
+    let mut asm = HelloReassembler::new();
+    assert!(asm.feed(&syn(2.0)).is_none());
+    assert!(asm.feed(&segment_at(2.1, 1400, &record[1400..])).is_none());
+    let hello = asm.feed(&segment_at(2.2, 0, &record[..1400])).unwrap();
+    assert_eq!(hello.sni.as_deref(), Some("reordered.example"));
+}
+
+#[test]
+fn stale_partial_hello_is_dropped() {
+    let record = client_hello("slow.example", &[], &[], 3000);
+    let mut asm = HelloReassembler::new();
+    assert!(asm.feed(&segment_at(1.0, 0, &record[..1400])).is_none());
+    // Resto chega muito depois: o começo já foi descartado
+    assert!(asm.feed(&segment_at(60.0, 1400, &record[1400..])).is_none());
+}
+
+#[test]
+fn log_line_carries_alpn_and_version() {
+    let line = "[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → www.youtube.com (142.250.0.1) | fonte=TLS | alpn=h2,http/1.1 | tls=1.3";
+    let entry = parse_log_line(line).unwrap();
+    assert_eq!(entry.source, "TLS");
+    assert_eq!(entry.alpn.as_deref(), Some("h2,http/1.1"));
+    assert_eq!(entry.tls_version.as_deref(), Some("1.3"));
+
+    let plain = parse_log_line("[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → a.com (1.2.3.4) | fonte=HTTP").unwrap();
+    assert!(plain.alpn.is_none() && plain.tls_version.is_none());
+}
+
+#[test]
+fn ja3_and_ja4_from_client_hello() {
+    let record = client_hello("example.com", &["h2", "http/1.1"], &[0x0a0a, 0x0304, 0x0303], 10);
+    let hello = HelloReassembler::new().feed(&segment(1.0, &record)).unwrap();
+    assert_eq!(hello.extensions, vec![0x0000, 0x0010, 0x002b, 0x0015]);
+    assert_eq!(hello.ja3_string(), "771,4865,0-16-43-21,,");
+    assert_eq!(hello.ja3(), "e2a3d7880c101af90c7ac8551a3a1350");
+    assert_eq!(hello.ja4(false), "t13d0104h2_0f2cb44170f4_7418e7913c75");
+    assert!(hello.ja4(true).starts_with("q13d0104h2_"));
+
+    let line = "[+] 2025-12-01 19:29:20 | 192.168.1.10 (tv) → example.com (142.250.0.1) | fonte=TLS | tls=1.3 | ja3=e2a3d7880c101af90c7ac8551a3a1350 | ja4=t13d0104h2_0f2cb44170f4_7418e7913c75";
+    let entry = parse_log_line(line).unwrap();
+    assert_eq!(entry.ja3.as_deref(), Some("e2a3d7880c101af90c7ac8551a3a1350"));
+    assert_eq!(entry.ja4.as_deref(), Some("t13d0104h2_0f2cb44170f4_7418e7913c75"));
+}